@@ -0,0 +1,109 @@
+use crossterm::event::KeyEvent;
+use tui::backend::Backend;
+use tui::Frame;
+use tui::layout::Rect;
+use tui::style::{Modifier, Style};
+use tui::widgets::{Block, Borders, List, ListItem, ListState};
+use crate::{App, DataManager, LayoutCommonTrait};
+
+/// Archived tasks of the selected group, the newest first, any of them can be moved back to the active tasks.
+pub struct ArchiveLayout {
+    selected: usize
+}
+
+impl ArchiveLayout {
+    pub fn new() -> ArchiveLayout {
+        ArchiveLayout {
+            selected: 0
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.selected = 0;
+    }
+}
+
+impl LayoutCommonTrait for ArchiveLayout {
+    fn handle_input(&mut self, data_manager: &mut DataManager, key_code: KeyEvent) {
+        if data_manager.get_group_items().is_empty() {
+            return;
+        }
+
+        let archived_count = data_manager.get_group_read_only(data_manager.selected_group).get_archive().len();
+        if archived_count == 0 {
+            return;
+        }
+
+        if data_manager.config.get_key("up_task_or_subtask") == key_code.code {
+            self.selected = self.selected.saturating_sub(1);
+        } else if data_manager.config.get_key("down_task_or_subtask") == key_code.code {
+            self.selected = (self.selected + 1).min(archived_count - 1);
+        } else if data_manager.config.get_key("restore_archived_task") == key_code.code {
+            data_manager.apply();
+
+            let selected_group = data_manager.selected_group;
+            data_manager.get_group(selected_group).restore_archived_task(archived_count - 1 - self.selected);
+            data_manager.load_folding(selected_group);
+            let _ = data_manager.save_state();
+
+            self.selected = self.selected.min(archived_count.saturating_sub(2));
+        }
+    }
+
+    fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>, frame_size: &Rect) {
+        ArchiveLayout::create_and_render_base_block(f, app, chunk);
+        ArchiveLayout::create_and_render_item_list(f, app, chunk, frame_size);
+    }
+
+    fn create_and_render_base_block<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>) {
+        let title = match app.data_manager.get_group_items().get(app.data_manager.selected_group) {
+            Some(group) => format!("Archive of {} (Enter to restore a task)", group.name),
+            None => "Archive".to_string()
+        };
+
+        let archive_block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .style(Style::default());
+
+        f.render_widget(archive_block, chunk[1]);
+    }
+
+    fn create_and_render_item_list<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>, _frame_size: &Rect) {
+        let area = Rect::new(chunk[1].x + 2, chunk[1].y + 1, chunk[1].width.saturating_sub(4), chunk[1].height.saturating_sub(2));
+        let archive = match app.data_manager.get_group_items().get(app.data_manager.selected_group) {
+            Some(group) => group.get_archive(),
+            None => return
+        };
+
+        let icon = app.data_manager.config.task.get("icon_completed").unwrap();
+        let selected_color = app.data_manager.config.get_color("task", "selected_color");
+        let completed_color = app.data_manager.config.get_color("task", "completed_color");
+
+        let mut items_list : Vec<ListItem> = Vec::new();
+        for (i, task) in archive.iter().rev().enumerate() {
+            let completed_at = task.completed_at.map(|completed_at| completed_at.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "-".repeat(16));
+            let sub_tasks = app.data_manager.get_group_read_only(app.data_manager.selected_group).get_tasks_and_subtasks_count_specific(task.get_tasks()).0;
+            let mut line = format!("{}  {}  {}", icon, completed_at, task.name);
+            if sub_tasks > 0 {
+                line = format!("{} ({} subtasks)", line, sub_tasks);
+            }
+
+            if i == app.archive_layout.selected {
+                items_list.push(ListItem::new(line).style(Style::default().fg(selected_color).add_modifier(Modifier::BOLD)));
+            } else {
+                items_list.push(ListItem::new(line).style(Style::default().fg(completed_color)));
+            }
+        }
+
+        if items_list.is_empty() {
+            items_list.push(ListItem::new("Nothing archived in this group, press 'z' on a completed task to archive it"));
+        }
+
+        let mut state = ListState::default();
+        state.select(Some(app.archive_layout.selected));
+        f.render_stateful_widget(List::new(items_list), area, &mut state);
+    }
+
+    fn create_and_render_edit_mode<B: Backend>(_f: &mut Frame<B>, _app: &mut App, _chunk: &Vec<Rect>) {  }
+}
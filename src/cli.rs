@@ -0,0 +1,253 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::data_manager::{DataManager, GroupItem, TaskItem};
+
+pub const SUBCOMMANDS: [&str; 5] = ["add", "done", "rm", "edit", "list"];
+
+const USAGE: &str = "Usage:
+  term_do add <group> <text> [--parent <id>]   adds a task, or a subtask of the task <id>, and prints its id
+  term_do done <group> <id>                    completes a task and its subtasks
+  term_do rm <group> <id>                      deletes a task and its subtasks
+  term_do edit <group> <id> <text>             changes the text of a task
+  term_do list [group] [--format plain|json|tsv]
+                                               lists the tasks of a group, or of all of them
+<group> is the name or the id of a group.";
+
+/// Runs a subcommand on the data file without opening the interface. Changes go through the same history as in the
+/// interface, so they can be undone from it.
+pub fn run_subcommand(args: &mut VecDeque<String>) -> Result<(), String> {
+    let subcommand = args.pop_front().unwrap_or_default();
+
+    let mut data_manager = DataManager::new();
+    data_manager.load_state()?;
+
+    return match subcommand.as_str() {
+        "add" => add(&mut data_manager, args),
+        "done" => done(&mut data_manager, args),
+        "rm" => remove(&mut data_manager, args),
+        "edit" => edit(&mut data_manager, args),
+        "list" => list(&data_manager, args),
+        _ => Err(USAGE.to_string())
+    };
+}
+
+fn add(data_manager: &mut DataManager, args: &mut VecDeque<String>) -> Result<(), String> {
+    let parent = take_option(args, "--parent")?.map(|parent| parse_id(&parent)).transpose()?;
+    let group_index = find_group(data_manager, args.pop_front())?;
+    let text = take_text(args)?;
+
+    if let Some(parent) = parent {
+        get_task(data_manager.get_group_read_only(group_index), parent)?;
+    }
+
+    data_manager.apply();
+    let gi = data_manager.get_group(group_index);
+    let task_id = match parent {
+        Some(parent) => gi.add_subtask(text, parent),
+        None => gi.add_task(text)
+    };
+    data_manager.save_state()?;
+
+    println!("{}", task_id);
+    return Ok(());
+}
+
+fn done(data_manager: &mut DataManager, args: &mut VecDeque<String>) -> Result<(), String> {
+    let group_index = find_group(data_manager, args.pop_front())?;
+    let task_id = take_task_id(data_manager.get_group_read_only(group_index), args)?;
+
+    data_manager.apply();
+    data_manager.get_group(group_index).set_task_and_subtasks_done_or_undone(task_id, Some(true));
+    data_manager.save_state()?;
+
+    return Ok(());
+}
+
+fn remove(data_manager: &mut DataManager, args: &mut VecDeque<String>) -> Result<(), String> {
+    let group_index = find_group(data_manager, args.pop_front())?;
+    let task_id = take_task_id(data_manager.get_group_read_only(group_index), args)?;
+
+    data_manager.apply();
+    data_manager.get_group(group_index).take_task(task_id);
+    data_manager.save_state()?;
+
+    return Ok(());
+}
+
+fn edit(data_manager: &mut DataManager, args: &mut VecDeque<String>) -> Result<(), String> {
+    let group_index = find_group(data_manager, args.pop_front())?;
+    let task_id = take_task_id(data_manager.get_group_read_only(group_index), args)?;
+    let text = take_text(args)?;
+
+    data_manager.apply();
+    data_manager.get_group(group_index).edit_sub_task(task_id, text);
+    data_manager.save_state()?;
+
+    return Ok(());
+}
+
+fn list(data_manager: &DataManager, args: &mut VecDeque<String>) -> Result<(), String> {
+    let format = take_option(args, "--format")?.unwrap_or("plain".to_string());
+    let groups : Vec<&GroupItem> = match args.pop_front() {
+        Some(group) => vec![data_manager.get_group_read_only(find_group(data_manager, Some(group))?)],
+        None => data_manager.get_group_items().iter().collect()
+    };
+
+    if !["plain", "json", "tsv"].contains(&format.as_str()) {
+        return Err(format!("Unknown format '{}', it can be plain, json or tsv", format));
+    }
+
+    // A reader that stops early, like head, closes the pipe, which only ends the listing
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    return match write_list(&mut out, &groups, &format) {
+        Err(error) if error.kind() != io::ErrorKind::BrokenPipe => Err(format!("Couldn't write the list: {}", error)),
+        _ => Ok(())
+    };
+}
+
+fn write_list(out: &mut impl Write, groups: &Vec<&GroupItem>, format: &str) -> io::Result<()> {
+    match format {
+        "plain" => {
+            for group in groups.iter() {
+                let (count, completed) = group.get_tasks_and_subtasks_count();
+                writeln!(out, "{} (id {}) {}/{}", group.name, group.get_id(), completed, count)?;
+                for (task, path) in group.get_tasks_with_paths() {
+                    writeln!(out, "{}[{}] {}  {}", "  ".repeat(path.len()), if task.done { "x" } else { " " }, task.id, task.name)?;
+                }
+            }
+        },
+        "json" => {
+            let groups_json : Vec<Value> = groups.iter().map(|group| {
+                let (count, completed) = group.get_tasks_and_subtasks_count();
+                let tasks : Vec<Value> = group.get_tasks_with_paths().into_iter().map(|(task, path)| {
+                    let (subtasks, completed_subtasks) = group.get_tasks_and_subtasks_count_specific(&task.tasks);
+                    return json!({
+                        "id": task.id,
+                        "parent": if task.parent == -1 { None } else { Some(task.parent) },
+                        "name": task.name,
+                        "done": task.done,
+                        "path": path,
+                        "depth": path.len() - 1,
+                        "subtasks": subtasks,
+                        "completed_subtasks": completed_subtasks
+                    });
+                }).collect();
+
+                return json!({
+                    "id": group.get_id(),
+                    "name": group.name,
+                    "tasks_count": count,
+                    "completed_count": completed,
+                    "tasks": tasks
+                });
+            }).collect();
+
+            writeln!(out, "{}", serde_json::to_string_pretty(&json!({ "version": 1, "groups": groups_json })).unwrap())?;
+        },
+        "tsv" => {
+            writeln!(out, "group_id\tgroup\ttask_id\tparent\tdone\tdepth\tpath\tsubtasks\tcompleted_subtasks")?;
+            for group in groups.iter() {
+                for (task, path) in group.get_tasks_with_paths() {
+                    let (subtasks, completed_subtasks) = group.get_tasks_and_subtasks_count_specific(&task.tasks);
+                    let path : Vec<String> = path.iter().map(|name| to_tsv_field(name)).collect();
+                    writeln!(out, "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}", group.get_id(), to_tsv_field(&group.name), task.id,
+                             if task.parent == -1 { String::new() } else { task.parent.to_string() }, task.done, path.len() - 1,
+                             path.join(" > "), subtasks, completed_subtasks)?;
+                }
+            }
+        },
+        _ => {}
+    }
+
+    return Ok(());
+}
+
+fn to_tsv_field(text: &str) -> String {
+    return text.replace(['\t', '\n', '\r'], " ");
+}
+
+/// Writes a group, or every group with `all`, to `file` in the format `export_groups` produces.
+pub fn export(group: &str, file: Option<String>, export_groups: fn(&Vec<&GroupItem>) -> String) -> Result<(), String> {
+    let file = file.ok_or("Exporting needs a group, or all, and the file to write")?;
+
+    let mut data_manager = DataManager::new();
+    data_manager.load_state()?;
+
+    let groups : Vec<&GroupItem> = if group.eq("all") {
+        data_manager.get_group_items().iter().collect()
+    } else {
+        vec![data_manager.get_group_read_only(find_group(&data_manager, Some(group.to_string()))?)]
+    };
+
+    return fs::write(&file, export_groups(&groups)).map_err(|error| format!("Couldn't write {}: {}", file, error));
+}
+
+/// Adds the tasks `import` reads from `file`, in a single step that can be undone. Tasks without a group go to one
+/// named after the file.
+pub fn import(file: &str, import: fn(&mut DataManager, &str, &str) -> Result<usize, String>) -> Result<(), String> {
+    let content = fs::read_to_string(file).map_err(|error| format!("Couldn't read {}: {}", file, error))?;
+    let default_group = Path::new(file).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or("Imported".to_string());
+
+    let mut data_manager = DataManager::new();
+    data_manager.load_state()?;
+
+    data_manager.apply();
+    let imported = import(&mut data_manager, &content, &default_group)?;
+    data_manager.save_state()?;
+
+    println!("Imported {} tasks from {}", imported, file);
+    return Ok(());
+}
+
+/// Finds a group by its name, ignoring case if there is no exact match, or by its id. Returns its index.
+fn find_group(data_manager: &DataManager, group: Option<String>) -> Result<usize, String> {
+    let group = group.ok_or(USAGE.to_string())?;
+    let groups = data_manager.get_group_items();
+
+    let index = groups.iter().position(|gi| gi.name.eq(&group))
+        .or_else(|| groups.iter().position(|gi| gi.name.eq_ignore_ascii_case(&group)))
+        .or_else(|| group.parse::<usize>().ok().and_then(|id| groups.iter().position(|gi| gi.get_id() == id)));
+
+    return index.ok_or(format!("There is no group '{}'", group));
+}
+
+fn get_task(group: &GroupItem, task_id: usize) -> Result<&TaskItem, String> {
+    return GroupItem::get_task_recursive_read_only(task_id, group.get_tasks()).map(|task| task.0).map_err(|_| format!("There is no task {} in group '{}'", task_id, group.name));
+}
+
+fn take_task_id(group: &GroupItem, args: &mut VecDeque<String>) -> Result<usize, String> {
+    let task_id = parse_id(&args.pop_front().ok_or(USAGE.to_string())?)?;
+    get_task(group, task_id)?;
+    return Ok(task_id);
+}
+
+fn parse_id(id: &str) -> Result<usize, String> {
+    return id.parse::<usize>().map_err(|_| format!("'{}' is not a task id", id));
+}
+
+/// The rest of the arguments joined, so the text doesn't need quotes.
+fn take_text(args: &mut VecDeque<String>) -> Result<String, String> {
+    let text = args.drain(..).collect::<Vec<String>>().join(" ");
+    if text.trim().is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    return Ok(text);
+}
+
+/// Removes `--option <value>` from anywhere in the arguments and returns the value.
+fn take_option(args: &mut VecDeque<String>, option: &str) -> Result<Option<String>, String> {
+    let position = match args.iter().position(|arg| arg.eq(option)) {
+        Some(position) => position,
+        None => return Ok(None)
+    };
+
+    args.remove(position);
+    return args.remove(position).map(Some).ok_or(format!("{} needs a value", option));
+}
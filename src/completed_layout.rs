@@ -0,0 +1,53 @@
+use chrono::{Duration, Local};
+use tui::backend::Backend;
+use tui::Frame;
+use tui::layout::Rect;
+use tui::style::Style;
+use tui::widgets::{Block, Borders, List, ListItem};
+use crate::{App, DataManager, LayoutCommonTrait};
+
+pub struct CompletedLayout;
+
+impl CompletedLayout {
+    pub fn new() -> CompletedLayout {
+        CompletedLayout {  }
+    }
+}
+
+impl LayoutCommonTrait for CompletedLayout {
+    fn handle_input(&mut self, _data_manager: &mut DataManager, _key_code: crossterm::event::KeyEvent) {  }
+
+    fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>, frame_size: &Rect) {
+        CompletedLayout::create_and_render_base_block(f, app, chunk);
+        CompletedLayout::create_and_render_item_list(f, app, chunk, frame_size);
+    }
+
+    fn create_and_render_base_block<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>) {
+        let days = app.data_manager.config.get_recently_completed_days();
+        let completed_block = Block::default()
+            .title(format!("Completed in the last {} days", days))
+            .borders(Borders::ALL)
+            .style(Style::default());
+
+        f.render_widget(completed_block, chunk[1]);
+    }
+
+    fn create_and_render_item_list<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>, _frame_size: &Rect) {
+        let area = Rect::new(chunk[1].x + 2, chunk[1].y + 1, chunk[1].width.saturating_sub(4), chunk[1].height.saturating_sub(2));
+        let since = Local::now().naive_local() - Duration::days(app.data_manager.config.get_recently_completed_days());
+
+        let mut items_list : Vec<ListItem> = Vec::new();
+        for (completed_at, group, path) in app.data_manager.get_tasks_completed_since(since) {
+            let line = format!("{}  {}  {} > {}", app.data_manager.config.task.get("icon_completed").unwrap(), completed_at.format("%Y-%m-%d %H:%M"), group, path);
+            items_list.push(ListItem::new(line).style(Style::default().fg(app.data_manager.config.get_color("task", "completed_color"))));
+        }
+
+        if items_list.is_empty() {
+            items_list.push(ListItem::new("Nothing completed yet in this period"));
+        }
+
+        f.render_widget(List::new(items_list), area);
+    }
+
+    fn create_and_render_edit_mode<B: Backend>(_f: &mut Frame<B>, _app: &mut App, _chunk: &Vec<Rect>) {  }
+}
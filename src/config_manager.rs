@@ -28,6 +28,7 @@ selected_color="(6, 152, 154)"
 non_selected_color="(255, 255, 255)"
 border_color="(255, 255, 255)"
 icon=""
+ask_before_delete="true"
 
 [task]
 selected_color="(196, 160, 0)"
@@ -36,9 +37,26 @@ completed_color="(78, 154, 6)"
 border_color="(255, 255, 255)"
 icon_uncompleted=""
 icon_completed=""
+icon_notes="✎"
 vertical_child_char_icon="║"
 turn_right_child_char_icon="╚"
 horizontal_child_char_icon="═"
+overdue_color="(204, 0, 0)"
+due_today_color="(245, 121, 0)"
+priority_low_icon="↓"
+priority_low_color="(114, 159, 207)"
+priority_medium_icon="→"
+priority_medium_color="(252, 233, 79)"
+priority_high_icon="↑"
+priority_high_color="(252, 175, 62)"
+priority_urgent_icon="‼"
+priority_urgent_color="(239, 41, 41)"
+recently_completed_days="7"
+undo_limit="100"
+; Actions older than this many days are forgotten, 0 keeps them however old they are
+undo_max_age_days="30"
+auto_archive_days="0"
+ask_before_delete="true"
 
 [key_bindings]
 add_group='a'
@@ -50,18 +68,44 @@ up_group="Up"
 add_task='a'
 add_subtask='A'
 delete_task_or_subtask='d'
+accept_confirmation='Y'
 edit_task_or_subtask='e'
 complete_or_uncomplete_task='c'
 apply_add_or_edit_task_or_subtask="Enter"
 down_task_or_subtask="Down"
 up_task_or_subtask="Up"
 fold_subtasks='f'
+set_due_date='D'
+raise_priority='+'
+lower_priority='-'
+sort_by_priority='p'
+filter_by_tags='#'
+edit_notes='i'
+apply_notes="Tab"
+move_task_up='K'
+move_task_down='J'
+indent_task="Tab"
+outdent_task="BackTab"
+move_task_to_group='m'
+copy_task_to_group='y'
+archive_task='z'
+show_archive='Z'
+restore_archived_task="Enter"
 undo='u'
 redo='r'
+search='/'
+apply_search="Enter"
+next_search_match='n'
+previous_search_match='N'
+recently_completed='l'
+undo_history='H'
+jump_in_history="Enter"
 
 [paths]
 settings_path='.'
 data_path='.'
+backup_count='3'
+storage='json'
             "#;
 
         if conf.is_err() {
@@ -69,7 +113,14 @@ data_path='.'
             fs::write("settings.ini", content).expect("Couldn't write contents");
         }
 
-        let (conf, task_conf, group_conf, input_conf, path_conf) = ConfigManager::load_config(content, "settings.ini");
+        let (conf, mut task_conf, mut group_conf, mut input_conf, mut path_conf) = ConfigManager::load_config(content, "settings.ini");
+
+        // Settings files from older versions lack the newer keys, those fall back to the default values
+        let default_conf = Ini::load_from_str(content).unwrap();
+        ConfigManager::fill_missing_keys(&mut task_conf, default_conf.section(Some("task")).unwrap());
+        ConfigManager::fill_missing_keys(&mut group_conf, default_conf.section(Some("group")).unwrap());
+        ConfigManager::fill_missing_keys(&mut input_conf, default_conf.section(Some("key_bindings")).unwrap());
+        ConfigManager::fill_missing_keys(&mut path_conf, default_conf.section(Some("paths")).unwrap());
 
         ConfigManager {
             ini: conf.clone(),
@@ -95,6 +146,37 @@ data_path='.'
         return Color::Rgb(values[0].trim().parse().unwrap(), values[1].trim().parse().unwrap(), values[2].trim().parse().unwrap());
     }
 
+    pub fn get_bool(&self, section: &str, key: &str) -> bool {
+        let properties : &Properties;
+        if section.eq("group") {
+            properties = &self.group;
+        } else {
+            properties = &self.task;
+        }
+
+        return properties.get(key).unwrap().trim().eq_ignore_ascii_case("true");
+    }
+
+    pub fn get_auto_archive_days(&self) -> i64 {
+        return self.task.get("auto_archive_days").unwrap().trim().parse().unwrap_or(0);
+    }
+
+    pub fn get_undo_limit(&self) -> usize {
+        return self.task.get("undo_limit").unwrap().trim().parse().unwrap_or(100);
+    }
+
+    pub fn get_undo_max_age_days(&self) -> i64 {
+        return self.task.get("undo_max_age_days").unwrap().trim().parse().unwrap_or(30);
+    }
+
+    pub fn get_recently_completed_days(&self) -> i64 {
+        return self.task.get("recently_completed_days").unwrap().trim().parse().unwrap_or(7);
+    }
+
+    pub fn get_backup_count(&self) -> usize {
+        return self.path.get("backup_count").unwrap().trim().parse().unwrap_or(3);
+    }
+
     pub fn get_key(&self, key: &str) -> KeyCode {
         let key_value = self.input.get(key).unwrap();
         return if key_value.len() > 1 {
@@ -123,6 +205,19 @@ data_path='.'
         }
     }
 
+    /// The key as written in settings.ini, to show it in messages.
+    pub fn get_key_name(&self, key: &str) -> String {
+        return self.input.get(key).unwrap().to_string();
+    }
+
+    fn fill_missing_keys(properties: &mut Properties, defaults: &Properties) {
+        for (key, value) in defaults.iter() {
+            if !properties.contains_key(key) {
+                properties.insert(key, value);
+            }
+        }
+    }
+
     fn load_config(content: &str, path_to_search: &str) -> (Ini, Properties, Properties, Properties, Properties) {
         let mut conf_conf = Ini::load_from_file(path_to_search).unwrap();
         let mut task_conf = conf_conf.section(Some("task"));
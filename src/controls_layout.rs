@@ -34,6 +34,10 @@ impl LayoutCommonTrait for ControlsLayout {
             Spans::from("Esc/'q' to quit app or to hide controls info"),
             Spans::from("'u' undo"),
             Spans::from("'r' redo"),
+            Spans::from("'H' to show or hide the undo history, Enter on an entry to go back to it (or forward, for undone entries)"),
+            Spans::from("'/' to search groups and tasks as you type, Enter to keep the search, Esc to clear it"),
+            Spans::from("'n' / 'N' to jump to the next/previous search match"),
+            Spans::from("'l' to show or hide the tasks completed in the last days (see recently_completed_days)"),
             Spans::from(""),
             Spans::from("-- Groups controls --"),
             Spans::from("UpArrow to select the upper group"),
@@ -44,7 +48,7 @@ impl LayoutCommonTrait for ControlsLayout {
             Spans::from("'e' to show input to edit selected group"),
             Spans::from("Enter on 'a' to apply name change to group"),
             Spans::from("Esc on 'a' to cancel the changes to group"),
-            Spans::from("'d' to delete a group (all tasks too)"),
+            Spans::from("'d' to delete a group (all tasks too), 'Y' to confirm (see ask_before_delete in [group])"),
             Spans::from(""),
             Spans::from("-- Tasks controls --"),
             Spans::from("UpArrow to select the upper task"),
@@ -58,9 +62,21 @@ impl LayoutCommonTrait for ControlsLayout {
             Spans::from("'e' to show input to edit selected task or subtask"),
             Spans::from("Enter on 'a' to apply name change to task or subtask"),
             Spans::from("Esc on 'a' to cancel the changes to task or subtask"),
-            Spans::from("'d' to delete a task (all subtasks too)"),
+            Spans::from("'d' to delete a task (all subtasks too), 'Y' to confirm (see ask_before_delete in [task])"),
+            Spans::from("'z' to archive a completed task with its subtasks (see auto_archive_days to do it automatically)"),
+            Spans::from("'Z' to show or hide the archive of the group, Enter to restore an archived task"),
             Spans::from("'f' to fold a tasks containing subtasks"),
             Spans::from("'c' to mark/unmark a task (and all subtasks) as completed"),
+            Spans::from("'D' to show input to set the due date of a task (YYYY-MM-DD [HH:MM])"),
+            Spans::from("Enter on 'D' with an empty input to clear the due date"),
+            Spans::from("'+' / '-' to raise/lower the priority of a task"),
+            Spans::from("'p' to show the tasks of the group sorted by priority (stored order is kept)"),
+            Spans::from("'#' to filter the tasks by the #tags and @tags written in their names"),
+            Spans::from("'i' to show and edit the notes of a task, Enter adds a new line"),
+            Spans::from("Tab on 'i' to save the notes, Esc on 'i' to cancel the changes"),
+            Spans::from("'K' / 'J' to move a task (and its subtasks) up/down among its siblings, not while sorted by priority"),
+            Spans::from("Tab to make a task a subtask of the task above it (not while sorted by priority), Shift+Tab to move it out of its parent"),
+            Spans::from("'m' / 'y' to move/copy a task (and its subtasks) to the group picked with Up/Down and Enter"),
 
         ];
 
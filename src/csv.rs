@@ -0,0 +1,25 @@
+use crate::data_manager::GroupItem;
+
+/// One row per task for spreadsheets, with the task tree flattened into its path ("Release > Docs > API") and depth,
+/// and how many of its subtasks (at any depth) are completed.
+pub fn export_groups(groups: &Vec<&GroupItem>) -> String {
+    let mut csv = String::from("group,path,depth,done,subtasks,completed_subtasks\r\n");
+    for group in groups {
+        for (task, path) in group.get_tasks_with_paths() {
+            let (subtasks, completed_subtasks) = group.get_tasks_and_subtasks_count_specific(&task.tasks);
+            csv.push_str(&format!("{},{},{},{},{},{}\r\n", to_csv_field(&group.name), to_csv_field(&path.join(" > ")), path.len() - 1,
+                                  task.done, subtasks, completed_subtasks));
+        }
+    }
+
+    return csv;
+}
+
+/// Quoted if needed as in RFC 4180, so names with commas, quotes or line breaks stay in one cell.
+fn to_csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", text.replace('"', "\"\""));
+    }
+
+    return text.to_string();
+}
@@ -1,13 +1,20 @@
 use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::fs;
-use std::fs::File;
+use std::time::SystemTime;
 
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
 use serde::{Deserialize, Serialize};
-use crate::enums::InputMode;
+use crate::enums::{InputMode, Priority};
 
 use unicode_width::UnicodeWidthStr;
 use crate::config_manager::ConfigManager;
-use crate::history::History;
+use crate::history::{History, HistoryEntry, Operation};
+use crate::migrations;
+use crate::migrations::CURRENT_DATA_VERSION;
+use crate::storage;
+use crate::storage::StorageBackend;
 
 pub struct LayoutCommon {
     pub(crate) input_mode: InputMode,
@@ -37,7 +44,7 @@ impl LayoutCommon {
     }
 
     pub fn is_in_edit_mode(&self) -> bool {
-        return self.input_mode == InputMode::Add || self.input_mode == InputMode::Edit;
+        return self.input_mode != InputMode::Navigate;
     }
 }
 
@@ -53,11 +60,19 @@ pub struct TaskItem {
     pub(crate) indentation: usize,
     pub(crate) parent: isize,
     pub(crate) tasks: Vec<TaskItem>,
-    pub(crate) folded: bool
+    pub(crate) folded: bool,
+    pub(crate) due_date: Option<NaiveDate>,
+    pub(crate) due_time: Option<NaiveTime>,
+    pub(crate) priority: Priority,
+    pub(crate) notes: String,
+    pub(crate) created_at: Option<NaiveDateTime>,
+    pub(crate) edited_at: Option<NaiveDateTime>,
+    pub(crate) completed_at: Option<NaiveDateTime>
 }
 
 impl TaskItem {
     pub fn new(task_name: String, id_value: usize, parent_id: isize) -> TaskItem {
+        let now = Local::now().naive_local();
         TaskItem {
             id: id_value,
             done: false,
@@ -65,7 +80,14 @@ impl TaskItem {
             indentation: 0,
             tasks: Vec::new(),
             parent: parent_id,
-            folded: false
+            folded: false,
+            due_date: None,
+            due_time: None,
+            priority: Priority::None,
+            notes: String::new(),
+            created_at: Some(now),
+            edited_at: Some(now),
+            completed_at: None
         }
     }
 
@@ -87,6 +109,79 @@ impl TaskItem {
         return completed;
     }
 
+    pub fn parse_due(text: &str) -> Result<(Option<NaiveDate>, Option<NaiveTime>), chrono::ParseError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok((None, None));
+        }
+
+        return match text.split_once(' ') {
+            Some((date, time)) => {
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+                let time = NaiveTime::parse_from_str(time.trim(), "%H:%M")?;
+                Ok((Some(date), Some(time)))
+            },
+            None => Ok((Some(NaiveDate::parse_from_str(text, "%Y-%m-%d")?), None))
+        };
+    }
+
+    pub fn get_due_string(&self) -> String {
+        return match (self.due_date, self.due_time) {
+            (Some(date), Some(time)) => format!("{} {}", date.format("%Y-%m-%d"), time.format("%H:%M")),
+            (Some(date), None) => date.format("%Y-%m-%d").to_string(),
+            _ => String::new()
+        };
+    }
+
+    pub fn is_overdue(&self, now: NaiveDateTime) -> bool {
+        if self.done {
+            return false;
+        }
+
+        return match (self.due_date, self.due_time) {
+            (Some(date), Some(time)) => date.and_time(time) < now,
+            (Some(date), None) => date < now.date(),
+            _ => false
+        };
+    }
+
+    pub fn is_due_today(&self, now: NaiveDateTime) -> bool {
+        if self.done || self.is_overdue(now) {
+            return false;
+        }
+
+        return self.due_date == Some(now.date());
+    }
+
+    pub fn get_tags(&self) -> Vec<String> {
+        return self.name.split_whitespace()
+            .map(|word| word.trim_end_matches([',', '.', ';', ':']).to_lowercase())
+            .filter(|word| word.len() > 1 && (word.starts_with('#') || word.starts_with('@')))
+            .collect();
+    }
+
+    pub fn has_tags(&self, tag_filter: &Vec<String>) -> bool {
+        let tags = self.get_tags();
+        return tag_filter.iter().all(|filter| {
+            tags.iter().any(|tag| tag.eq(filter) || tag[1..].eq(filter.as_str()))
+        });
+    }
+
+    /// Keeps `completed_at` in sync, it is only set when the task goes from pending to done.
+    pub fn set_done(&mut self, done: bool) {
+        if done && !self.done {
+            self.completed_at = Some(Local::now().naive_local());
+        } else if !done {
+            self.completed_at = None;
+        }
+
+        self.done = done;
+    }
+
+    pub fn touch(&mut self) {
+        self.edited_at = Some(Local::now().naive_local());
+    }
+
     pub fn fold(&mut self) {
         if self.tasks.is_empty() {
             return;
@@ -94,28 +189,66 @@ impl TaskItem {
 
         self.folded = !self.folded;
     }
+
+    pub fn get_fields(&self) -> TaskItem {
+        TaskItem {
+            id: self.id,
+            done: self.done,
+            name: self.name.clone(),
+            indentation: 0,
+            tasks: Vec::new(),
+            parent: -1,
+            folded: self.folded,
+            due_date: self.due_date,
+            due_time: self.due_time,
+            priority: self.priority,
+            notes: self.notes.clone(),
+            created_at: self.created_at,
+            edited_at: self.edited_at,
+            completed_at: self.completed_at
+        }
+    }
+
+    pub fn has_same_fields(&self, other: &TaskItem) -> bool {
+        return self.done == other.done && self.name == other.name && self.folded == other.folded && self.due_date == other.due_date &&
+            self.due_time == other.due_time && self.priority == other.priority && self.notes == other.notes &&
+            self.created_at == other.created_at && self.edited_at == other.edited_at && self.completed_at == other.completed_at;
+    }
 }
 
 
 
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct GroupItem {
     id: usize,
     pub name: String,
-    tasks: Vec<TaskItem>
+    tasks: Vec<TaskItem>,
+    next_task_id: usize,
+    pub sorted_by_priority: bool,
+    archive: Vec<TaskItem>
 }
 
 impl GroupItem {
-    pub fn new(data_manager: &DataManager) -> GroupItem {
+    pub fn new(data_manager: &mut DataManager) -> GroupItem {
+        let id = data_manager.next_group_id;
+        data_manager.next_group_id += 1;
+
         GroupItem {
-            id: data_manager.get_group_items().len(),
+            id,
             name: String::new(),
-            tasks: Vec::new()
+            tasks: Vec::new(),
+            next_task_id: 0,
+            sorted_by_priority: false,
+            archive: Vec::new()
         }
     }
 
+    pub fn get_id(&self) -> usize {
+        return self.id;
+    }
+
     pub fn get_tasks(&self) -> &Vec<TaskItem> {
         return &self.tasks;
     }
@@ -124,36 +257,230 @@ impl GroupItem {
         return &mut self.tasks;
     }
 
-    pub fn add_task(&mut self, task_name: String) {
-        let task = TaskItem::new(task_name, GroupItem::get_tasks_and_subtasks_count(self).0, -1);
+    pub fn add_task(&mut self, task_name: String) -> usize {
+        let task = TaskItem::new(task_name, self.generate_task_id(), -1);
+        let new_id = task.id;
         self.tasks.push(task);
+
+        return new_id;
     }
 
     pub  fn remove_task(&mut self, task: (&TaskItem, isize)) -> usize {
-        let amount_removed: usize;
+        let amount_removed = GroupItem::get_tasks_and_subtasks_count_recursive(&task.0.get_tasks()).0 + 1;
         if task.0.parent != -1 {
             let parent = GroupItem::get_task_recursive(task.0.parent as usize, &mut self.tasks).unwrap();
-            amount_removed = GroupItem::get_tasks_and_subtasks_count_recursive(&task.0.get_tasks()).0 + 1;
             parent.0.tasks.remove(task.1 as usize);
         } else {
-            amount_removed = GroupItem::get_tasks_and_subtasks_count_recursive(&task.0.get_tasks()).0 + 1;
             self.tasks.remove(task.1 as usize);
         }
 
-        GroupItem::recalculate_tasks_ids_on_remove(&mut self.tasks,task.0.id, amount_removed);
-
         return amount_removed;
     }
 
     pub  fn add_subtask(&mut self, task_name: String, parent_id: usize) -> usize {
+        let new_id = self.generate_task_id();
         let parent_task = GroupItem::get_task_recursive(parent_id, &mut self.tasks).unwrap();
-        let mut new_task = TaskItem::new(task_name, parent_id + GroupItem::get_tasks_and_subtasks_count_recursive(&parent_task.0.tasks).0 + 1, parent_id as isize);
+        let mut new_task = TaskItem::new(task_name, new_id, parent_id as isize);
         new_task.indentation = parent_task.0.indentation + 1;
-        let new_id = new_task.id;
         parent_task.0.tasks.push(new_task);
 
-        GroupItem::recalculate_tasks_ids_on_add(parent_id, new_id, &mut self.tasks, false);
-        GroupItem::recalculate_parent_tasks_ids_on_add(0, &mut self.tasks);
+        return new_id;
+    }
+
+    pub fn insert_task_at(&mut self, parent: isize, index: usize, mut task: TaskItem) {
+        let indentation = if parent == -1 { 0 } else { GroupItem::get_task_recursive_read_only(parent as usize, &self.tasks).unwrap().0.indentation + 1 };
+        task.parent = parent;
+        GroupItem::set_indentation_recursive(&mut task, indentation);
+        self.get_siblings_mut(parent).insert(index, task);
+    }
+
+    pub fn remove_task_at(&mut self, parent: isize, index: usize) -> TaskItem {
+        return self.get_siblings_mut(parent).remove(index);
+    }
+
+    pub fn set_task_fields(&mut self, fields: &TaskItem) {
+        let task = GroupItem::get_task_recursive(fields.id, &mut self.tasks).unwrap().0;
+        let tasks = std::mem::take(&mut task.tasks);
+        let (parent, indentation) = (task.parent, task.indentation);
+
+        *task = fields.clone();
+        task.tasks = tasks;
+        task.parent = parent;
+        task.indentation = indentation;
+    }
+
+    pub fn get_fields(&self) -> GroupItem {
+        GroupItem {
+            id: self.id,
+            name: self.name.clone(),
+            tasks: Vec::new(),
+            next_task_id: self.next_task_id,
+            sorted_by_priority: self.sorted_by_priority,
+            archive: Vec::new()
+        }
+    }
+
+    pub fn has_same_fields(&self, other: &GroupItem) -> bool {
+        return self.name == other.name && self.next_task_id == other.next_task_id && self.sorted_by_priority == other.sorted_by_priority;
+    }
+
+    pub fn set_fields(&mut self, fields: &GroupItem) {
+        self.name = fields.name.clone();
+        self.next_task_id = fields.next_task_id;
+        self.sorted_by_priority = fields.sorted_by_priority;
+    }
+
+    /// Operations that turn the tasks of `before` into the ones of `after`. Tasks are matched by id, so a task that
+    /// changed place is recorded as a move and not as removing and adding it again. None if the change can't be told
+    /// as adding, removing, moving and editing whole subtrees.
+    pub fn diff_tasks(before: &GroupItem, after: &GroupItem) -> Option<Vec<Operation>> {
+        let mut operations : Vec<Operation> = Vec::new();
+        let mut old_tasks : Vec<&TaskItem> = Vec::new();
+        let mut new_tasks : Vec<&TaskItem> = Vec::new();
+        GroupItem::collect_tasks_recursive(&before.tasks, &mut old_tasks);
+        GroupItem::collect_tasks_recursive(&after.tasks, &mut new_tasks);
+        let old_by_id : HashMap<usize, &TaskItem> = old_tasks.iter().map(|task| (task.id, *task)).collect();
+        let new_by_id : HashMap<usize, &TaskItem> = new_tasks.iter().map(|task| (task.id, *task)).collect();
+
+        // A kept task must hang from a kept task, both before and after
+        for task in &new_tasks {
+            if let Some(old_task) = old_by_id.get(&task.id) {
+                if (task.parent != -1 && !old_by_id.contains_key(&(task.parent as usize))) || (old_task.parent != -1 && !new_by_id.contains_key(&(old_task.parent as usize))) {
+                    return None;
+                }
+            }
+        }
+
+        let mut children : HashMap<isize, Vec<usize>> = HashMap::new();
+        let mut parents : HashMap<usize, isize> = HashMap::new();
+        children.insert(-1, before.tasks.iter().map(|task| task.id).collect());
+        for task in &old_tasks {
+            children.insert(task.id as isize, task.tasks.iter().map(|sub_task| sub_task.id).collect());
+            parents.insert(task.id, task.parent);
+        }
+
+        for task in &old_tasks {
+            if !new_by_id.contains_key(&task.id) && (task.parent == -1 || new_by_id.contains_key(&(task.parent as usize))) {
+                let siblings = children.get_mut(&task.parent).unwrap();
+                let index = siblings.iter().position(|id| *id == task.id).unwrap();
+                siblings.remove(index);
+                operations.push(Operation::RemoveTask { group_id: after.id, parent: task.parent, index, task: (*task).clone() });
+            }
+        }
+
+        // Parents are placed before their children, so a task is never moved inside itself
+        let mut new_parents : Vec<isize> = vec![-1];
+        new_parents.extend(new_tasks.iter().filter(|task| old_by_id.contains_key(&task.id)).map(|task| task.id as isize));
+        for parent in new_parents {
+            let new_children = if parent == -1 { &after.tasks } else { &new_by_id[&(parent as usize)].tasks };
+            let kept_children = new_children.iter().filter(|task| old_by_id.contains_key(&task.id));
+
+            for (index, task) in kept_children.enumerate() {
+                let old_parent = parents[&task.id];
+                let old_index = children[&old_parent].iter().position(|id| *id == task.id).unwrap();
+                if old_parent != parent || old_index != index {
+                    children.get_mut(&old_parent).unwrap().remove(old_index);
+                    children.entry(parent).or_default().insert(index, task.id);
+                    parents.insert(task.id, parent);
+                    operations.push(Operation::MoveTask { group_id: after.id, task_id: task.id, from: (old_parent, old_index), to: (parent, index) });
+                }
+            }
+        }
+
+        for task in &new_tasks {
+            if !old_by_id.contains_key(&task.id) && (task.parent == -1 || old_by_id.contains_key(&(task.parent as usize))) {
+                let siblings = if task.parent == -1 { &after.tasks } else { &new_by_id[&(task.parent as usize)].tasks };
+                let index = siblings.iter().position(|sibling| sibling.id == task.id).unwrap();
+                operations.push(Operation::AddTask { group_id: after.id, parent: task.parent, index, task: (*task).clone() });
+            }
+        }
+
+        for task in &new_tasks {
+            if let Some(old_task) = old_by_id.get(&task.id) {
+                if !old_task.has_same_fields(task) {
+                    operations.push(Operation::EditTask { group_id: after.id, before: old_task.get_fields(), after: task.get_fields() });
+                }
+            }
+        }
+
+        return Some(operations);
+    }
+
+    pub fn get_archive(&self) -> &Vec<TaskItem> {
+        return &self.archive;
+    }
+
+    pub fn archive_task(&mut self, task_id: usize) -> bool {
+        if !GroupItem::get_task_recursive_read_only(task_id, &self.tasks).unwrap().0.done {
+            return false;
+        }
+
+        // The task keeps the id of its parent so restoring it can put it back there
+        let mut task = self.take_task(task_id);
+        GroupItem::set_indentation_recursive(&mut task, 0);
+        self.archive.push(task);
+
+        return true;
+    }
+
+    pub fn archive_completed_before(&mut self, before: NaiveDateTime) -> usize {
+        let mut task_ids : Vec<usize> = Vec::new();
+        GroupItem::get_completed_before_recursive(&self.tasks, before, &mut task_ids);
+
+        for task_id in &task_ids {
+            self.archive_task(*task_id);
+        }
+
+        return task_ids.len();
+    }
+
+    pub fn restore_archived_task(&mut self, index: usize) -> usize {
+        let task = self.archive.remove(index);
+        let task_id = task.id;
+        let parent = if task.parent != -1 && GroupItem::get_task_recursive_read_only(task.parent as usize, &self.tasks).is_ok() { task.parent } else { -1 };
+        let siblings_count = self.get_siblings_mut(parent).len();
+        self.insert_task_at(parent, siblings_count, task);
+
+        return task_id;
+    }
+
+    pub fn diff_archive(before: &GroupItem, after: &GroupItem) -> Vec<Operation> {
+        let mut operations : Vec<Operation> = Vec::new();
+        let mut archived_ids : Vec<usize> = before.archive.iter().map(|task| task.id).collect();
+
+        for task in &before.archive {
+            if !after.archive.iter().any(|new_task| new_task.id == task.id) {
+                let index = archived_ids.iter().position(|id| *id == task.id).unwrap();
+                archived_ids.remove(index);
+                operations.push(Operation::RemoveArchivedTask { group_id: after.id, index, task: task.clone() });
+            }
+        }
+
+        for (index, task) in after.archive.iter().enumerate() {
+            if !archived_ids.contains(&task.id) {
+                operations.push(Operation::AddArchivedTask { group_id: after.id, index, task: task.clone() });
+            }
+        }
+
+        return operations;
+    }
+
+    pub fn take_task(&mut self, task_id: usize) -> TaskItem {
+        let task = GroupItem::get_task_recursive_read_only(task_id, &self.tasks).unwrap();
+        let task = (task.0.clone(), task.1);
+        self.remove_task((&task.0, task.1));
+
+        if task.0.parent != -1 {
+            self.update_parents_to_check_if_all_completed(task.0.parent as usize);
+        }
+
+        return task.0;
+    }
+
+    pub fn add_task_tree(&mut self, mut task: TaskItem) -> usize {
+        self.assign_new_ids_recursive(&mut task, -1, 0);
+        let new_id = task.id;
+        self.tasks.push(task);
 
         return new_id;
     }
@@ -161,6 +488,137 @@ impl GroupItem {
     pub  fn edit_sub_task(&mut self, task_id: usize, new_text: String) {
         let task = GroupItem::get_task_recursive(task_id, &mut self.tasks).unwrap();
         task.0.name = new_text;
+        task.0.touch();
+    }
+
+    pub fn get_flattened_task_ids(&self) -> Vec<usize> {
+        let mut ids : Vec<usize> = Vec::new();
+        self.flatten_task_ids_recursive(&self.tasks, &mut ids);
+        return ids;
+    }
+
+    pub fn get_task_id_at_position(&self, position: usize) -> Option<usize> {
+        return self.get_flattened_task_ids().get(position).copied();
+    }
+
+    pub fn get_task_position(&self, task_id: usize) -> Option<usize> {
+        return self.get_flattened_task_ids().iter().position(|id| *id == task_id);
+    }
+
+    pub fn get_tasks_with_paths(&self) -> Vec<(&TaskItem, Vec<&str>)> {
+        let mut tasks : Vec<(&TaskItem, Vec<&str>)> = Vec::new();
+        GroupItem::get_tasks_with_paths_recursive(&self.tasks, &Vec::new(), &mut tasks);
+        return tasks;
+    }
+
+    pub fn raise_task_priority(&mut self, task_id: usize) {
+        let task = GroupItem::get_task_recursive(task_id, &mut self.tasks).unwrap();
+        task.0.priority = task.0.priority.raise();
+        task.0.touch();
+    }
+
+    pub fn lower_task_priority(&mut self, task_id: usize) {
+        let task = GroupItem::get_task_recursive(task_id, &mut self.tasks).unwrap();
+        task.0.priority = task.0.priority.lower();
+        task.0.touch();
+    }
+
+    /// Siblings in the order they are drawn, sorting by priority only changes the view and never the stored order.
+    pub fn get_ordered_tasks<'a>(&self, tasks: &'a Vec<TaskItem>) -> Vec<&'a TaskItem> {
+        let mut ordered : Vec<&TaskItem> = tasks.iter().collect();
+        if self.sorted_by_priority {
+            ordered.sort_by_key(|task| std::cmp::Reverse(task.priority));
+        }
+
+        return ordered;
+    }
+
+    pub fn get_task_ids_filtered_out(&self, tag_filter: &Vec<String>) -> HashSet<usize> {
+        let mut filtered_out : HashSet<usize> = HashSet::new();
+        if !tag_filter.is_empty() {
+            GroupItem::filter_by_tags_recursive(&self.tasks, tag_filter, &mut filtered_out);
+        }
+
+        return filtered_out;
+    }
+
+    pub fn move_task(&mut self, task_id: usize, up: bool) -> bool {
+        let (parent, index) = GroupItem::get_parent_and_index(task_id, &self.tasks);
+        let siblings = self.get_siblings_mut(parent);
+
+        if up {
+            if index == 0 {
+                return false;
+            }
+            siblings.swap(index, index - 1);
+        } else {
+            if index + 1 >= siblings.len() {
+                return false;
+            }
+            siblings.swap(index, index + 1);
+        }
+
+        return true;
+    }
+
+    pub fn indent_task(&mut self, task_id: usize) -> bool {
+        let (parent, index) = GroupItem::get_parent_and_index(task_id, &self.tasks);
+        if index == 0 {
+            return false;
+        }
+
+        let siblings = self.get_siblings_mut(parent);
+        let mut task = siblings.remove(index);
+        let new_parent = &mut siblings[index - 1];
+        task.parent = new_parent.id as isize;
+        GroupItem::set_indentation_recursive(&mut task, new_parent.indentation + 1);
+        new_parent.folded = false;
+        new_parent.tasks.push(task);
+
+        let new_parent_id = new_parent.id;
+        self.update_parents_to_check_if_all_completed(new_parent_id);
+
+        return true;
+    }
+
+    pub fn outdent_task(&mut self, task_id: usize) -> bool {
+        let (parent, index) = GroupItem::get_parent_and_index(task_id, &self.tasks);
+        if parent == -1 {
+            return false;
+        }
+
+        let (grandparent, parent_index) = GroupItem::get_parent_and_index(parent as usize, &self.tasks);
+        let mut task = self.get_siblings_mut(parent).remove(index);
+        task.parent = grandparent;
+        let indentation = task.indentation - 1;
+        GroupItem::set_indentation_recursive(&mut task, indentation);
+        self.get_siblings_mut(grandparent).insert(parent_index + 1, task);
+
+        self.update_parents_to_check_if_all_completed(parent as usize);
+
+        return true;
+    }
+
+    pub fn unfold_parents(&mut self, task_id: usize) {
+        let mut top_parent = GroupItem::get_task_recursive(task_id, &mut self.tasks).unwrap().0.parent;
+        while top_parent != -1 {
+            let top_parent_task = GroupItem::get_task_recursive(top_parent as usize, &mut self.tasks).unwrap();
+            top_parent_task.0.folded = false;
+            top_parent = top_parent_task.0.parent;
+        }
+    }
+
+    pub fn set_task_notes(&mut self, task_id: usize, notes: String) {
+        let task = GroupItem::get_task_recursive(task_id, &mut self.tasks).unwrap();
+        task.0.notes = notes;
+        task.0.touch();
+    }
+
+    pub fn set_task_due(&mut self, task_id: usize, due: (Option<NaiveDate>, Option<NaiveTime>)) {
+        let task = GroupItem::get_task_recursive(task_id, &mut self.tasks).unwrap();
+        task.0.due_date = due.0;
+        task.0.due_time = due.1;
+        task.0.touch();
     }
 
     pub fn get_tasks_and_subtasks_count(&self) -> (usize, usize) {
@@ -174,9 +632,10 @@ impl GroupItem {
     pub  fn set_task_and_subtasks_done_or_undone(&mut self, task_id: usize, completed: Option<bool>) {
         let parent_task = GroupItem::get_task_recursive(task_id, &mut self.tasks).unwrap();
         if completed.is_some() {
-            parent_task.0.done = completed.unwrap();
+            parent_task.0.set_done(completed.unwrap());
         } else {
-            parent_task.0.done = !parent_task.0.done;
+            let done = !parent_task.0.done;
+            parent_task.0.set_done(done);
         }
         GroupItem::set_task_completed_recursive(parent_task.0.done, &mut parent_task.0.tasks);
 
@@ -185,7 +644,7 @@ impl GroupItem {
         while top_parent != -1 {
             let top_parent_task = GroupItem::get_task_recursive(top_parent as usize, &mut self.tasks).unwrap();
             all_done &= top_parent_task.0.are_all_sub_tasks_done(top_parent_task.0.get_tasks());
-            top_parent_task.0.done = all_done;
+            top_parent_task.0.set_done(all_done);
             top_parent = top_parent_task.0.parent;
         }
     }
@@ -194,13 +653,13 @@ impl GroupItem {
         let parent_task = GroupItem::get_task_recursive(task_id, &mut self.tasks).unwrap();
         let mut all_done = true;
         all_done &= parent_task.0.are_all_sub_tasks_done(parent_task.0.get_tasks());
-        parent_task.0.done = all_done;
+        parent_task.0.set_done(all_done);
         let mut top_parent = parent_task.0.parent;
 
         while top_parent != -1 {
             let top_parent_task = GroupItem::get_task_recursive(top_parent as usize, &mut self.tasks).unwrap();
             all_done &= top_parent_task.0.are_all_sub_tasks_done(top_parent_task.0.get_tasks());
-            top_parent_task.0.done = all_done;
+            top_parent_task.0.set_done(all_done);
             top_parent = top_parent_task.0.parent;
         }
     }
@@ -246,42 +705,110 @@ impl GroupItem {
 
     fn set_task_completed_recursive(completed: bool, tasks: &mut Vec<TaskItem>) {
         for i in 0..tasks.len() {
-            tasks[i].done = completed;
+            tasks[i].set_done(completed);
             GroupItem::set_task_completed_recursive(completed, &mut tasks[i].tasks);
         }
     }
 
-    fn recalculate_tasks_ids_on_add(parent_id: usize, new_id: usize, tasks: &mut Vec<TaskItem>, found: bool) {
-        for i in 0..tasks.len() {
-            if tasks[i].id >= new_id  {
-                tasks[i].id += 1;
-            }
+    fn generate_task_id(&mut self) -> usize {
+        let id = self.next_task_id;
+        self.next_task_id += 1;
+        return id;
+    }
+
+    pub fn get_tasks_completed_since(&self, since: NaiveDateTime) -> Vec<(NaiveDateTime, String)> {
+        let mut completed : Vec<(NaiveDateTime, String)> = Vec::new();
+        GroupItem::get_tasks_completed_since_recursive(&self.tasks, since, "", &mut completed);
+        return completed;
+    }
 
-            if tasks[i].id != parent_id {
-                GroupItem::recalculate_tasks_ids_on_add(parent_id, new_id, &mut tasks[i].tasks, found);
+    fn get_tasks_completed_since_recursive(tasks: &Vec<TaskItem>, since: NaiveDateTime, path: &str, completed: &mut Vec<(NaiveDateTime, String)>) {
+        for task in tasks {
+            let task_path = if path.is_empty() { task.name.clone() } else { format!("{} > {}", path, task.name) };
+            if let Some(completed_at) = task.completed_at {
+                if task.done && completed_at >= since {
+                    completed.push((completed_at, task_path.clone()));
+                }
             }
+
+            GroupItem::get_tasks_completed_since_recursive(&task.tasks, since, task_path.as_str(), completed);
         }
     }
 
-    fn recalculate_parent_tasks_ids_on_add(parent_id: usize, tasks: &mut Vec<TaskItem>) {
-        for i in 0..tasks.len() {
-            GroupItem::recalculate_parent_tasks_ids_on_add(tasks[i].id, &mut tasks[i].tasks);
-            if tasks[i].parent != -1 {
-                tasks[i].parent = parent_id as isize;
+    fn assign_new_ids_recursive(&mut self, task: &mut TaskItem, parent: isize, indentation: usize) {
+        task.id = self.generate_task_id();
+        task.parent = parent;
+        task.indentation = indentation;
+
+        let id = task.id;
+        for sub_task in task.tasks.iter_mut() {
+            self.assign_new_ids_recursive(sub_task, id as isize, indentation + 1);
+        }
+    }
+
+    fn get_parent_and_index(task_id: usize, tasks: &Vec<TaskItem>) -> (isize, usize) {
+        let task = GroupItem::get_task_recursive_read_only(task_id, tasks).unwrap();
+        return (task.0.parent, task.1 as usize);
+    }
+
+    fn get_siblings_mut(&mut self, parent: isize) -> &mut Vec<TaskItem> {
+        if parent == -1 {
+            return &mut self.tasks;
+        }
+
+        return &mut GroupItem::get_task_recursive(parent as usize, &mut self.tasks).unwrap().0.tasks;
+    }
+
+    fn get_completed_before_recursive(tasks: &Vec<TaskItem>, before: NaiveDateTime, task_ids: &mut Vec<usize>) {
+        for task in tasks {
+            match task.completed_at {
+                Some(completed_at) if task.done && completed_at < before => task_ids.push(task.id),
+                _ => GroupItem::get_completed_before_recursive(&task.tasks, before, task_ids)
             }
         }
     }
 
-    fn recalculate_tasks_ids_on_remove(tasks: &mut Vec<TaskItem>, removed_id: usize, amount_removed: usize) {
-        for i in 0..tasks.len() {
-            if tasks[i].id > removed_id  {
-                tasks[i].id -= amount_removed;
-                if tasks[i].parent > removed_id as isize {
-                    tasks[i].parent -= amount_removed as isize;
-                }
+    fn collect_tasks_recursive<'a>(tasks: &'a Vec<TaskItem>, collected: &mut Vec<&'a TaskItem>) {
+        for task in tasks {
+            collected.push(task);
+            GroupItem::collect_tasks_recursive(&task.tasks, collected);
+        }
+    }
+
+    fn set_indentation_recursive(task: &mut TaskItem, indentation: usize) {
+        task.indentation = indentation;
+        for sub_task in task.tasks.iter_mut() {
+            GroupItem::set_indentation_recursive(sub_task, indentation + 1);
+        }
+    }
+
+    fn filter_by_tags_recursive(tasks: &Vec<TaskItem>, tag_filter: &Vec<String>, filtered_out: &mut HashSet<usize>) -> bool {
+        let mut any_kept = false;
+        for task in tasks {
+            let sub_task_kept = GroupItem::filter_by_tags_recursive(&task.tasks, tag_filter, filtered_out);
+            if sub_task_kept || task.has_tags(tag_filter) {
+                any_kept = true;
+            } else {
+                filtered_out.insert(task.id);
             }
+        }
+
+        return any_kept;
+    }
 
-            GroupItem::recalculate_tasks_ids_on_remove(&mut tasks[i].tasks, removed_id, amount_removed);
+    fn flatten_task_ids_recursive(&self, tasks: &Vec<TaskItem>, ids: &mut Vec<usize>) {
+        for task in self.get_ordered_tasks(tasks) {
+            ids.push(task.id);
+            self.flatten_task_ids_recursive(&task.tasks, ids);
+        }
+    }
+
+    fn get_tasks_with_paths_recursive<'a>(tasks: &'a Vec<TaskItem>, parent_path: &Vec<&'a str>, result: &mut Vec<(&'a TaskItem, Vec<&'a str>)>) {
+        for task in tasks {
+            let mut path = parent_path.clone();
+            path.push(&task.name);
+            result.push((task, path.clone()));
+            GroupItem::get_tasks_with_paths_recursive(&task.tasks, &path, result);
         }
     }
 
@@ -312,49 +839,126 @@ impl GroupItem {
 
 
 
+/// The data before a change, a group is only copied the first time it is changed.
+struct StateBeforeChange {
+    group_ids: Vec<usize>,
+    groups: HashMap<usize, GroupItem>,
+    next_group_id: usize,
+    selection: (usize, usize)
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct DataManager {
+    version: u64,
     groups: Vec<GroupItem>,
     pub selected_group: usize,
     pub selected_task: usize,
+    next_group_id: usize,
 
     #[serde(skip)]
     pub folded_state: HashMap<usize, HashSet<usize>>,
     #[serde(skip)]
+    pub tag_filter: Vec<String>,
+    #[serde(skip)]
+    pub search_query: String,
+    #[serde(skip)]
     history: History,
     #[serde(skip)]
-    pub config: ConfigManager
+    state_before_change: Option<StateBeforeChange>,
+    #[serde(skip)]
+    changed_groups: HashSet<usize>,
+    #[serde(skip)]
+    group_hashes: HashMap<usize, u64>,
+    #[serde(skip)]
+    pub config: ConfigManager,
+    #[serde(skip)]
+    storage: Option<Box<dyn StorageBackend>>,
+    #[serde(skip)]
+    save_error: Option<String>,
+    #[serde(skip)]
+    pub status_message: Option<String>
 }
 
 impl DataManager {
     pub fn new() -> DataManager {
+        let config = ConfigManager::new();
         DataManager {
+            version: CURRENT_DATA_VERSION,
             groups: Vec::new(),
             selected_group: 0,
             selected_task: 0,
+            next_group_id: 0,
             folded_state: HashMap::new(),
-            history: History::new(),
-            config: ConfigManager::new()
+            tag_filter: Vec::new(),
+            search_query: String::new(),
+            history: History::new(config.get_undo_limit(), config.get_undo_max_age_days()),
+            state_before_change: None,
+            changed_groups: HashSet::new(),
+            group_hashes: HashMap::new(),
+            config,
+            storage: None,
+            save_error: None,
+            status_message: None
         }
     }
 
     pub fn add_group_item(&mut self, group_item: GroupItem) {
         self.groups.push(group_item);
+        self.change_group(self.groups.len() - 1);
+    }
+
+    pub fn find_or_add_group(&mut self, name: &str) -> usize {
+        if let Some(index) = self.groups.iter().position(|gi| gi.name.eq(name)) {
+            return index;
+        }
+
+        let mut gi = GroupItem::new(self);
+        gi.name = name.to_string();
+        self.add_group_item(gi);
+
+        return self.groups.len() - 1;
     }
 
     pub fn edit_group_item(&mut self, group_id: usize, new_text: String) {
+        self.change_group(group_id);
         self.groups[group_id].name = new_text;
     }
 
     pub fn delete_group_item(&mut self, group_id: usize) {
+        self.change_group(group_id);
         self.groups.remove(group_id);
     }
 
+    pub fn move_task_to_group(&mut self, task_id: usize, from_group: usize, to_group: usize, copy: bool) -> usize {
+        let task = if copy {
+            GroupItem::get_task_recursive_read_only(task_id, self.groups[from_group].get_tasks()).unwrap().0.clone()
+        } else {
+            self.change_group(from_group);
+            self.groups[from_group].take_task(task_id)
+        };
+
+        self.change_group(to_group);
+        return self.groups[to_group].add_task_tree(task);
+    }
+
+    pub fn get_tasks_completed_since(&self, since: NaiveDateTime) -> Vec<(NaiveDateTime, String, String)> {
+        let mut completed : Vec<(NaiveDateTime, String, String)> = Vec::new();
+        for group in &self.groups {
+            for (completed_at, path) in group.get_tasks_completed_since(since) {
+                completed.push((completed_at, group.name.clone(), path));
+            }
+        }
+
+        completed.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+        return completed;
+    }
+
     pub fn get_group_items(&self) -> &Vec<GroupItem> {
         return &self.groups;
     }
 
     pub fn get_group(&mut self, id: usize) -> &mut GroupItem {
+        self.change_group(id);
         return &mut self.groups[id];
     }
 
@@ -362,69 +966,442 @@ impl DataManager {
         return &self.groups[id];
     }
 
-    pub fn load_state(&mut self) {
-        let read_file = fs::read_to_string(format!("{}/data.json", self.config.path.get("data_path").unwrap()));
-        match read_file {
-            Err(_error) => {
-                File::create(format!("{}/data.json", self.config.path.get("data_path").unwrap())).expect("Couldn't create file data.json");
-                let base_data_manager = DataManager::new();
-                let full_json = serde_json::to_string_pretty(&base_data_manager).expect("Couldn't serialized");
-                fs::write(format!("{}/data.json", self.config.path.get("data_path").unwrap()), full_json).expect("Couldn't write to data file");
+    /// Permanent id of the task under the cursor, `selected_task` is only its position on screen.
+    pub fn get_selected_task_id(&self) -> Option<usize> {
+        if self.groups.is_empty() {
+            return None;
+        }
+
+        return self.groups[self.selected_group].get_task_id_at_position(self.selected_task);
+    }
+
+    pub fn get_task_ids_filtered_out(&self) -> HashSet<usize> {
+        if self.groups.is_empty() {
+            return HashSet::new();
+        }
+
+        return self.groups[self.selected_group].get_task_ids_filtered_out(&self.tag_filter);
+    }
+
+    pub fn get_hidden_positions(&self) -> HashSet<usize> {
+        let mut hidden : HashSet<usize> = HashSet::new();
+        for (_, entry) in self.folded_state.iter() {
+            hidden.extend(entry);
+        }
+
+        let filtered_out = self.get_task_ids_filtered_out();
+        if !filtered_out.is_empty() {
+            let ids = self.groups[self.selected_group].get_flattened_task_ids();
+            for (position, id) in ids.iter().enumerate() {
+                if filtered_out.contains(id) {
+                    hidden.insert(position);
+                }
+            }
+        }
+
+        return hidden;
+    }
+
+    pub fn is_selected_task_hidden(&self) -> bool {
+        return self.get_hidden_positions().contains(&self.selected_task);
+    }
+
+    pub fn select_visible_task(&mut self) {
+        if self.groups.is_empty() || !self.is_selected_task_hidden() {
+            return;
+        }
+
+        let hidden = self.get_hidden_positions();
+        let tasks_count = self.groups[self.selected_group].get_tasks_and_subtasks_count().0;
+        if let Some(position) = (0..tasks_count).find(|position| !hidden.contains(position)) {
+            self.selected_task = position;
+        }
+    }
+
+    pub fn matches_search(&self, name: &str) -> bool {
+        if self.search_query.is_empty() {
+            return false;
+        }
+
+        return name.to_lowercase().contains(self.search_query.to_lowercase().as_str());
+    }
+
+    /// Every group and task matching the search as (group index, position), position 0 is the group name
+    /// itself and tasks start at 1, so the list is in the same order they appear on screen.
+    pub fn get_search_matches(&self) -> Vec<(usize, usize)> {
+        let mut matches : Vec<(usize, usize)> = Vec::new();
+        for (group_index, group) in self.groups.iter().enumerate() {
+            if self.matches_search(group.name.as_str()) {
+                matches.push((group_index, 0));
+            }
+
+            let filtered_out = group.get_task_ids_filtered_out(&self.tag_filter);
+            for (position, task_id) in group.get_flattened_task_ids().iter().enumerate() {
+                let task = GroupItem::get_task_recursive_read_only(*task_id, group.get_tasks()).unwrap().0;
+                if !filtered_out.contains(task_id) && self.matches_search(task.name.as_str()) {
+                    matches.push((group_index, position + 1));
+                }
+            }
+        }
+
+        return matches;
+    }
+
+    pub fn select_search_match(&mut self, from_task: bool, forward: bool, include_current: bool) -> Option<bool> {
+        let matches = self.get_search_matches();
+        if matches.is_empty() {
+            return None;
+        }
+
+        let current = (self.selected_group, if from_task { self.selected_task + 1 } else { 0 });
+        let found = if forward {
+            matches.iter().find(|m| if include_current { **m >= current } else { **m > current }).unwrap_or(&matches[0])
+        } else {
+            matches.iter().rev().find(|m| if include_current { **m <= current } else { **m < current }).unwrap_or(matches.last().unwrap())
+        };
+        let (group_index, position) = *found;
+
+        self.selected_group = group_index;
+        if position == 0 {
+            self.selected_task = 0;
+            self.load_folding(group_index);
+            return Some(false);
+        }
+
+        let task_id = self.groups[group_index].get_task_id_at_position(position - 1).unwrap();
+        self.change_group(group_index);
+        self.groups[group_index].unfold_parents(task_id);
+        self.selected_task = position - 1;
+        self.load_folding(group_index);
+
+        return Some(true);
+    }
+
+    pub fn load_state(&mut self) -> Result<(), String> {
+        let data_file = self.get_data_file_path();
+        match self.get_storage().read(&data_file) {
+            Ok(None) => {
+                let fields = DataManager::new().get_fields();
+                self.get_storage().write(&fields, &Vec::new(), &HashMap::new()).map_err(|error| format!("Couldn't create {}: {}", data_file, error))?;
+            },
+            Err(error) => {
+                return Err(format!("Couldn't load {}: {}", data_file, error));
             },
-            Ok(file) => {
-                let full_json : DataManager = serde_json::from_str(&file).unwrap();
-                self.groups = full_json.groups;
-                if !self.groups.is_empty() {
-                    self.load_folding(0);
+            Ok(Some(data)) => {
+                let version = self.load_from_value(data).map_err(|error| format!("Couldn't load {}: {}", data_file, error))?;
+
+                // Older files are upgraded on disk right away, the original is kept as <data file>.v<version>. The undo
+                // history of an older version is not loaded, its operations hold tasks in the old layout
+                if version < CURRENT_DATA_VERSION {
+                    let backup_file = format!("{}.v{}", data_file, version);
+                    fs::copy(&data_file, &backup_file).map_err(|error| format!("Couldn't back up {} before upgrading it: {}", data_file, error))?;
+                    // The upgraded data is loaded even if it can't be written yet, the error is shown until a save works
+                    let _ = self.save_state();
+                } else {
+                    // The groups are stored as they were read
+                    self.changed_groups.clear();
+                    self.load_history();
                 }
+
+                self.archive_old_completed_tasks();
             }
         }
+
+        return Ok(());
     }
 
-    pub fn save_state(&mut self) {
-        if !DataManager::check_data_integrity(self) {
-            eprintln!("Data integrity has been compromised! No serialization is being applied...");
+    fn archive_old_completed_tasks(&mut self) {
+        let days = self.config.get_auto_archive_days();
+        if days <= 0 {
             return;
         }
-        let full_json = serde_json::to_string_pretty(self).expect("Couldn't serialized");
-        fs::write(format!("{}/data.json", self.config.path.get("data_path").unwrap()), full_json).expect("Couldn't write to data file");
+
+        self.apply();
+        let before = Local::now().naive_local() - chrono::Duration::days(days);
+        let mut archived = 0;
+        for index in 0..self.groups.len() {
+            let mut task_ids : Vec<usize> = Vec::new();
+            GroupItem::get_completed_before_recursive(&self.groups[index].tasks, before, &mut task_ids);
+            if !task_ids.is_empty() {
+                self.change_group(index);
+                archived += self.groups[index].archive_completed_before(before);
+            }
+        }
+
+        if archived == 0 {
+            self.state_before_change = None;
+            return;
+        }
+
+        self.reload_selection();
+        let _ = self.save_state();
+    }
+
+    pub fn load_from_value(&mut self, data: serde_json::Value) -> Result<u64, String> {
+        let (full_json, version) = DataManager::parse_data(data)?;
+        self.groups = full_json.groups;
+        self.next_group_id = full_json.next_group_id;
+        self.selected_group = 0;
+        self.selected_task = 0;
+
+        self.changed_groups = self.groups.iter().map(|group| group.id).collect();
+        self.group_hashes.clear();
+        for group in &self.groups {
+            let group_json = serde_json::to_value(group).map_err(|error| error.to_string())?;
+            self.group_hashes.insert(group.id, DataManager::get_group_hash(&group_json));
+        }
+        if !self.groups.is_empty() {
+            self.load_folding(0);
+        }
+
+        return Ok(version);
+    }
+
+    pub fn parse_data(mut data: serde_json::Value) -> Result<(DataManager, u64), String> {
+        let version = migrations::get_data_version(&data);
+        migrations::migrate(&mut data)?;
+
+        let full_json : DataManager = serde_json::from_value(data).map_err(|error| error.to_string())?;
+        return Ok((full_json, version));
+    }
+
+    pub fn move_data_file_aside(&mut self) -> Result<String, String> {
+        return storage::move_file_aside(&self.get_data_file_path());
+    }
+
+    pub fn load_latest_backup(&mut self) -> Result<usize, String> {
+        for (number, _, _) in self.get_backups() {
+            let backup_file = format!("{}.{}", self.get_data_file_path(), number);
+            if let Ok(Some(data)) = self.get_storage().read(&backup_file) {
+                if self.load_from_value(data).is_ok() {
+                    return Ok(number);
+                }
+            }
+        }
+
+        return Err("There is no valid backup to open".to_string());
+    }
+
+    /// Writes the data, the error is also kept until a save works so the interface can show it. The change stays in
+    /// memory when the write fails (a full disk, a read only directory...), and the next save writes it again.
+    pub fn save_state(&mut self) -> Result<(), String> {
+        self.record_change();
+
+        let result = if !DataManager::check_data_integrity(self) {
+            Err("Data integrity has been compromised! No serialization is being applied...".to_string())
+        } else {
+            let data_file = self.get_data_file_path();
+            self.write_changed_groups().map_err(|error| format!("Couldn't save {}: {}", data_file, error))
+        };
+
+        self.save_error = result.clone().err();
+        if result.is_ok() {
+            self.save_history();
+        }
+
+        return result;
+    }
+
+    pub fn get_save_error(&self) -> Option<&String> {
+        return self.save_error.as_ref();
+    }
+
+    fn write_changed_groups(&mut self) -> Result<(), String> {
+        let mut changed_groups : HashMap<u64, serde_json::Value> = HashMap::new();
+        for group in self.groups.iter().filter(|group| self.changed_groups.contains(&group.id)) {
+            changed_groups.insert(group.id as u64, serde_json::to_value(group).map_err(|error| error.to_string())?);
+        }
+
+        let group_ids : Vec<u64> = self.groups.iter().map(|group| group.id as u64).collect();
+        let fields = self.get_fields();
+        self.get_storage().write(&fields, &group_ids, &changed_groups)?;
+
+        for (id, group_json) in &changed_groups {
+            self.group_hashes.insert(*id as usize, DataManager::get_group_hash(group_json));
+        }
+        self.group_hashes.retain(|id, _| group_ids.contains(&(*id as u64)));
+        self.changed_groups.clear();
+        return Ok(());
+    }
+
+    fn get_fields(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut fields = serde_json::Map::new();
+        fields.insert("version".to_string(), serde_json::json!(self.version));
+        fields.insert("selected_group".to_string(), serde_json::json!(self.selected_group));
+        fields.insert("selected_task".to_string(), serde_json::json!(self.selected_task));
+        fields.insert("next_group_id".to_string(), serde_json::json!(self.next_group_id));
+        return fields;
+    }
+
+    /// Called before changing a group, so it is written on the next save and, while a change is being recorded, its
+    /// state before the change is kept for the history.
+    fn change_group(&mut self, index: usize) {
+        let group = &self.groups[index];
+        self.changed_groups.insert(group.id);
+
+        if let Some(state) = &mut self.state_before_change {
+            if state.group_ids.contains(&group.id) && !state.groups.contains_key(&group.id) {
+                state.groups.insert(group.id, group.clone());
+            }
+        }
+    }
+
+    pub fn get_history_file_path(&mut self) -> String {
+        return format!("{}.history", self.get_data_file_path());
+    }
+
+    fn load_history(&mut self) {
+        let content = match fs::read_to_string(self.get_history_file_path()) {
+            Ok(content) => content,
+            Err(_) => return
+        };
+
+        if let Some(history) = History::load(&content, self.get_data_hash(), self.config.get_undo_limit(), self.config.get_undo_max_age_days()) {
+            self.history = history;
+        }
+    }
+
+    fn save_history(&mut self) {
+        let data_hash = self.get_data_hash();
+        let (lines, rewrite) = self.history.get_unsaved_lines(data_hash);
+        if lines.is_empty() {
+            return;
+        }
+
+        let history_file = self.get_history_file_path();
+        let content = lines.join("\n") + "\n";
+        let result = if rewrite {
+            storage::write_file_atomically(&history_file, content.as_bytes(), 0)
+        } else {
+            storage::append_to_file(&history_file, content.as_bytes())
+        };
+
+        // Losing the undo history isn't worth stopping the app for, a failed write only makes the next one rewrite the file
+        match result {
+            Ok(_) => self.history.set_saved(lines.len(), rewrite, data_hash),
+            Err(_) => self.history.set_file_outdated()
+        }
+    }
+
+    fn get_data_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for group in &self.groups {
+            group.id.hash(&mut hasher);
+            self.group_hashes.get(&group.id).hash(&mut hasher);
+        }
+        self.next_group_id.hash(&mut hasher);
+        return hasher.finish();
+    }
+
+    fn get_group_hash(group_json: &serde_json::Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        group_json.to_string().hash(&mut hasher);
+        return hasher.finish();
+    }
+
+    pub fn get_data_file_path(&mut self) -> String {
+        return self.get_storage().get_file_path();
+    }
+
+    pub fn create_data_file(&mut self, data_path: &str) -> Result<String, String> {
+        self.config.path.insert("data_path", data_path);
+        self.storage = None;
+
+        // An existing file is left alone, the storage would only write over part of a database
+        let data_file = self.get_data_file_path();
+        if fs::metadata(&data_file).is_ok() {
+            return Err(format!("There is already a data file at {}", data_file));
+        }
+
+        self.write_changed_groups().map_err(|error| format!("Couldn't create {}: {}", data_file, error))?;
+        return Ok(data_file);
+    }
+
+    pub fn get_backups(&mut self) -> Vec<(usize, SystemTime, u64)> {
+        return storage::get_backups(&self.get_data_file_path(), self.config.get_backup_count());
+    }
+
+    pub fn restore_backup(&mut self, number: usize) -> Result<(), String> {
+        let backup_file = format!("{}.{}", self.get_data_file_path(), number);
+        let data = self.get_storage().read(&backup_file)?.ok_or(format!("{} is empty", backup_file))?;
+        DataManager::parse_data(data).map_err(|error| format!("{} is not a valid data file: {}", backup_file, error))?;
+
+        return storage::restore_backup(&self.get_data_file_path(), number, self.config.get_backup_count());
     }
 
+    /// Remembers the data before a change, `save_state` records in the history what the change did. The groups are
+    /// copied as they get changed, see `change_group`.
     pub fn apply(&mut self) {
-        self.history.apply(serde_json::to_string_pretty(self).expect("Couldn't serialized"));
+        self.state_before_change = Some(StateBeforeChange {
+            group_ids: self.groups.iter().map(|group| group.id).collect(),
+            groups: HashMap::new(),
+            next_group_id: self.next_group_id,
+            selection: (self.selected_group, self.selected_task)
+        });
+    }
+
+    pub fn get_history(&self) -> &History {
+        return &self.history;
+    }
+
+    pub fn undo_steps(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.undo();
+        }
+    }
+
+    pub fn redo_steps(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.redo();
+        }
     }
 
     pub fn undo(&mut self) {
-        let undo = self.history.undo(serde_json::to_string_pretty(self).expect("Couldn't serialized"));
-        if undo.is_ok() {
-            let full_json : DataManager = serde_json::from_str(undo.unwrap().as_str()).unwrap();
-            self.groups = full_json.groups;
-            self.selected_group = full_json.selected_group;
-            self.selected_task = full_json.selected_task;
-            self.load_folding(full_json.selected_group);
+        if let Some(entry) = self.history.undo() {
+            for operation in entry.operations.iter().rev() {
+                DataManager::apply_operation(&mut self.groups, &operation.inverse());
+                self.changed_groups.extend(operation.get_group_id());
+            }
+            self.next_group_id = entry.next_group_id.0;
+            self.selected_group = entry.selection_before.0;
+            self.selected_task = entry.selection_before.1;
+            self.reload_selection();
         }
     }
 
     pub fn redo(&mut self) {
-        let redo = self.history.redo(serde_json::to_string_pretty(self).expect("Couldn't serialized"));
-        if redo.is_ok() {
-            let full_json : DataManager = serde_json::from_str(redo.unwrap().as_str()).unwrap();
-            self.groups = full_json.groups;
-            self.selected_group = full_json.selected_group;
-            self.selected_task = full_json.selected_task;
-            self.load_folding(full_json.selected_group);
+        if let Some(entry) = self.history.redo() {
+            for operation in &entry.operations {
+                DataManager::apply_operation(&mut self.groups, operation);
+                self.changed_groups.extend(operation.get_group_id());
+            }
+            self.next_group_id = entry.next_group_id.1;
+            self.selected_group = entry.selection_after.0;
+            self.selected_task = entry.selection_after.1;
+            self.reload_selection();
         }
     }
 
     pub fn check_data_integrity(&self) -> bool {
         let mut integrity_ok = true;
+        let mut group_ids : HashSet<usize> = HashSet::new();
 
         for group in &self.groups {
-            if group.tasks.is_empty() {
+            integrity_ok &= group_ids.insert(group.id) && group.id < self.next_group_id;
+
+            // The other groups were checked when they were changed
+            if !self.changed_groups.contains(&group.id) {
                 continue;
             }
 
-            integrity_ok &= DataManager::check_data_integrity_recursive(&group.tasks, 0).0;
+            let mut task_ids : HashSet<usize> = HashSet::new();
+            integrity_ok &= DataManager::check_data_integrity_recursive(&group.tasks, -1, group.next_task_id, &mut task_ids);
+
+            // Archived tasks remember the parent they had, so only their subtasks are checked against it
+            for task in &group.archive {
+                integrity_ok &= task_ids.insert(task.id) && task.id < group.next_task_id;
+                integrity_ok &= DataManager::check_data_integrity_recursive(&task.tasks, task.id as isize, group.next_task_id, &mut task_ids);
+            }
         }
 
         return integrity_ok;
@@ -443,7 +1420,8 @@ impl DataManager {
             return;
         }
 
-        let task = GroupItem::get_task_recursive_read_only(selected_task, gi.get_tasks()).unwrap();
+        let task_id = gi.get_task_id_at_position(selected_task).unwrap();
+        let task = GroupItem::get_task_recursive_read_only(task_id, gi.get_tasks()).unwrap();
         if task.0.folded {
             let elements_to_skip = gi.get_tasks_and_subtasks_count_specific(task.0.get_tasks()).0;
 
@@ -460,32 +1438,369 @@ impl DataManager {
 
 
 
-    fn check_data_integrity_recursive(tasks: &Vec<TaskItem>, mut id: usize) -> (bool, usize) {
+    fn record_change(&mut self) {
+        let state = match self.state_before_change.take() {
+            Some(state) => state,
+            None => return
+        };
+
+        // Groups that weren't changed weren't copied, they are the same item before and after the change
+        let current_groups : HashMap<usize, &GroupItem> = self.groups.iter().map(|group| (group.id, group)).collect();
+        let before : Vec<&GroupItem> = state.group_ids.iter().filter_map(|id| state.groups.get(id).or(current_groups.get(id).copied())).collect();
+        let after : Vec<&GroupItem> = self.groups.iter().collect();
+
+        let operations = DataManager::diff_groups(&before, &after);
+        if operations.is_empty() && state.next_group_id == self.next_group_id {
+            return;
+        }
+
+        self.history.apply(HistoryEntry {
+            created_at: Local::now().naive_local(),
+            label: DataManager::describe_change(&before, &after, &operations),
+            operations,
+            next_group_id: (state.next_group_id, self.next_group_id),
+            selection_before: state.selection,
+            selection_after: (self.selected_group, self.selected_task)
+        });
+    }
+
+    fn describe_change(before: &Vec<&GroupItem>, after: &Vec<&GroupItem>, operations: &Vec<Operation>) -> String {
+        let group_name = |group_id: usize| after.iter().chain(before.iter()).find(|group| group.id == group_id).map(|group| group.name.clone()).unwrap_or_default();
+        let task_name = |group_id: usize, task_id: usize| after.iter().find(|group| group.id == group_id)
+            .and_then(|group| GroupItem::get_task_recursive_read_only(task_id, &group.tasks).ok())
+            .map(|task| task.0.name.clone()).unwrap_or_default();
+
+        let added_groups : Vec<&GroupItem> = operations.iter().filter_map(|operation| if let Operation::AddGroup { group, .. } = operation { Some(group) } else { None }).collect();
+        let removed_groups : Vec<&GroupItem> = operations.iter().filter_map(|operation| if let Operation::RemoveGroup { group, .. } = operation { Some(group) } else { None }).collect();
+        let added_tasks : Vec<(usize, &TaskItem)> = operations.iter().filter_map(|operation| if let Operation::AddTask { group_id, task, .. } = operation { Some((*group_id, task)) } else { None }).collect();
+        let removed_tasks : Vec<(usize, &TaskItem)> = operations.iter().filter_map(|operation| if let Operation::RemoveTask { group_id, task, .. } = operation { Some((*group_id, task)) } else { None }).collect();
+        let moved_tasks : Vec<(usize, usize)> = operations.iter().filter_map(|operation| if let Operation::MoveTask { group_id, task_id, .. } = operation { Some((*group_id, *task_id)) } else { None }).collect();
+        let archived_tasks : Vec<(usize, &TaskItem)> = operations.iter().filter_map(|operation| if let Operation::AddArchivedTask { group_id, task, .. } = operation { Some((*group_id, task)) } else { None }).collect();
+        let restored_tasks : Vec<(usize, &TaskItem)> = operations.iter().filter_map(|operation| if let Operation::RemoveArchivedTask { group_id, task, .. } = operation { Some((*group_id, task)) } else { None }).collect();
+        let edited_tasks : Vec<(usize, &TaskItem, &TaskItem)> = operations.iter().filter_map(|operation| if let Operation::EditTask { group_id, before, after } = operation { Some((*group_id, before, after)) } else { None }).collect();
+
+        if let Some(group) = removed_groups.iter().find(|group| !added_groups.iter().any(|added| added.id == group.id)) {
+            return format!("Deleted group '{}'", group.name);
+        }
+
+        let new_groups : Vec<&&GroupItem> = added_groups.iter().filter(|group| !removed_groups.iter().any(|removed| removed.id == group.id)).collect();
+        match new_groups.len() {
+            0 => {},
+            1 => return format!("Added group '{}'", new_groups[0].name),
+            count => return format!("Added {} groups", count)
+        }
+
+        for operation in operations {
+            if let Operation::EditGroup { before, after } = operation {
+                if before.name != after.name {
+                    return format!("Renamed group '{}' to '{}'", before.name, after.name);
+                }
+                if before.sorted_by_priority != after.sorted_by_priority {
+                    let sorting = if after.sorted_by_priority { "Sorted" } else { "Stopped sorting" };
+                    return format!("{} group {} by priority", sorting, after.name);
+                }
+            }
+        }
+
+        for (verb, tasks) in [("Archived", &archived_tasks), ("Restored", &restored_tasks)] {
+            match tasks.len() {
+                0 => {},
+                1 => return format!("{} task '{}' in group {}", verb, tasks[0].1.name, group_name(tasks[0].0)),
+                count => return format!("{} {} tasks in group {}", verb, count, group_name(tasks[0].0))
+            }
+        }
+
+        if let (Some((from_group, task)), Some((to_group, _))) = (removed_tasks.first(), added_tasks.first()) {
+            if from_group != to_group {
+                return format!("Moved task '{}' from group {} to group {}", task.name, group_name(*from_group), group_name(*to_group));
+            }
+        }
+
+        if let Some((group_id, task)) = removed_tasks.first() {
+            if removed_tasks.len() > 1 {
+                return format!("Deleted {} tasks in group {}", removed_tasks.len(), group_name(*group_id));
+            }
+            return format!("Deleted task '{}' in group {}", task.name, group_name(*group_id));
+        }
+
+        if let Some((group_id, task)) = added_tasks.first() {
+            if added_tasks.len() > 1 {
+                return format!("Added {} tasks in group {}", added_tasks.len(), group_name(*group_id));
+            }
+            return format!("Added task '{}' in group {}", task.name, group_name(*group_id));
+        }
+
+        if let Some((group_id, task_id)) = moved_tasks.first() {
+            return format!("Moved task '{}' in group {}", task_name(*group_id, *task_id), group_name(*group_id));
+        }
+
+        let completed : Vec<&TaskItem> = edited_tasks.iter().filter(|(_, before, after)| !before.done && after.done).map(|(_, _, after)| *after).collect();
+        let uncompleted : Vec<&TaskItem> = edited_tasks.iter().filter(|(_, before, after)| before.done && !after.done).map(|(_, _, after)| *after).collect();
+        for (verb, tasks) in [("Completed", &completed), ("Uncompleted", &uncompleted)] {
+            match tasks.len() {
+                0 => {},
+                1 => return format!("{} task '{}'", verb, tasks[0].name),
+                count => return format!("{} {} tasks", verb, count)
+            }
+        }
+
+        if let Some((group_id, before, after)) = edited_tasks.first() {
+            let group = group_name(*group_id);
+            if before.name != after.name {
+                return format!("Renamed task '{}' to '{}' in group {}", before.name, after.name, group);
+            }
+            if before.notes != after.notes {
+                return format!("Edited the notes of '{}' in group {}", after.name, group);
+            }
+            if before.due_date != after.due_date || before.due_time != after.due_time {
+                if after.due_date.is_none() {
+                    return format!("Removed the due date of '{}' in group {}", after.name, group);
+                }
+                return format!("Set the due date of '{}' to {} in group {}", after.name, after.get_due_string(), group);
+            }
+            if before.priority != after.priority {
+                return format!("Changed the priority of '{}' to {} in group {}", after.name, after.priority.config_name(), group);
+            }
+            if before.folded != after.folded {
+                let folding = if after.folded { "Folded" } else { "Unfolded" };
+                return format!("{} task '{}' in group {}", folding, after.name, group);
+            }
+            return format!("Edited task '{}' in group {}", after.name, group);
+        }
+
+        return "Changed the data".to_string();
+    }
+
+    fn diff_groups(before: &Vec<&GroupItem>, after: &Vec<&GroupItem>) -> Vec<Operation> {
+        let mut operations : Vec<Operation> = Vec::new();
+        let before_ids : Vec<usize> = before.iter().map(|group| group.id).collect();
+        let after_ids : Vec<usize> = after.iter().map(|group| group.id).collect();
+        let mut ids = before_ids.clone();
+
+        for index in (0..before.len()).rev() {
+            if !after_ids.contains(&before[index].id) {
+                operations.push(Operation::RemoveGroup { index, group: before[index].clone() });
+                ids.remove(index);
+            }
+        }
+
+        let kept_ids : Vec<usize> = after_ids.iter().filter(|id| before_ids.contains(id)).copied().collect();
+        for (to, id) in kept_ids.iter().enumerate() {
+            let from = ids.iter().position(|other| other == id).unwrap();
+            if from != to {
+                ids.remove(from);
+                ids.insert(to, *id);
+                operations.push(Operation::MoveGroup { from, to });
+            }
+        }
+
+        for (index, group) in after.iter().copied().enumerate() {
+            if !before_ids.contains(&group.id) {
+                operations.push(Operation::AddGroup { index, group: group.clone() });
+            }
+        }
+
+        for (index, group) in after.iter().copied().enumerate() {
+            let old_group = match before.iter().copied().find(|old_group| old_group.id == group.id) {
+                Some(old_group) => old_group,
+                None => continue
+            };
+            if std::ptr::eq(old_group, group) {
+                continue;
+            }
+
+            match GroupItem::diff_tasks(old_group, group) {
+                Some(task_operations) => {
+                    if !old_group.has_same_fields(group) {
+                        operations.push(Operation::EditGroup { before: old_group.get_fields(), after: group.get_fields() });
+                    }
+                    operations.extend(task_operations);
+                    operations.extend(GroupItem::diff_archive(old_group, group));
+                },
+                None => {
+                    operations.push(Operation::RemoveGroup { index, group: old_group.clone() });
+                    operations.push(Operation::AddGroup { index, group: group.clone() });
+                }
+            }
+        }
+
+        return operations;
+    }
+
+    pub(crate) fn apply_operation(groups: &mut Vec<GroupItem>, operation: &Operation) {
+        match operation {
+            Operation::AddGroup { index, group } => groups.insert(*index, group.clone()),
+            Operation::RemoveGroup { index, .. } => {
+                groups.remove(*index);
+            },
+            Operation::MoveGroup { from, to } => {
+                let group = groups.remove(*from);
+                groups.insert(*to, group);
+            },
+            Operation::EditGroup { after, .. } => DataManager::find_group(groups, after.id).set_fields(after),
+            Operation::AddTask { group_id, parent, index, task } => DataManager::find_group(groups, *group_id).insert_task_at(*parent, *index, task.clone()),
+            Operation::RemoveTask { group_id, parent, index, .. } => {
+                DataManager::find_group(groups, *group_id).remove_task_at(*parent, *index);
+            },
+            Operation::MoveTask { group_id, from, to, .. } => {
+                let group = DataManager::find_group(groups, *group_id);
+                let task = group.remove_task_at(from.0, from.1);
+                group.insert_task_at(to.0, to.1, task);
+            },
+            Operation::EditTask { group_id, after, .. } => DataManager::find_group(groups, *group_id).set_task_fields(after),
+            Operation::AddArchivedTask { group_id, index, task } => DataManager::find_group(groups, *group_id).archive.insert(*index, task.clone()),
+            Operation::RemoveArchivedTask { group_id, index, .. } => {
+                DataManager::find_group(groups, *group_id).archive.remove(*index);
+            }
+        }
+    }
+
+    fn find_group(groups: &mut Vec<GroupItem>, group_id: usize) -> &mut GroupItem {
+        return groups.iter_mut().find(|group| group.id == group_id).unwrap();
+    }
+
+    fn reload_selection(&mut self) {
+        if self.groups.is_empty() {
+            self.selected_group = 0;
+            self.selected_task = 0;
+            return;
+        }
+
+        self.selected_group = self.selected_group.min(self.groups.len() - 1);
+        let tasks_count = self.groups[self.selected_group].get_flattened_task_ids().len();
+        self.selected_task = self.selected_task.min(tasks_count.saturating_sub(1));
+        self.load_folding(self.selected_group);
+    }
+
+    fn check_data_integrity_recursive(tasks: &Vec<TaskItem>, parent: isize, next_task_id: usize, ids: &mut HashSet<usize>) -> bool {
         let mut ok = true;
 
         for task in tasks {
-            if id != task.id {
-                ok &= false;
-            }
+            ok &= ids.insert(task.id);
+            ok &= task.id < next_task_id;
+            ok &= task.parent == parent;
+
+            ok &= DataManager::check_data_integrity_recursive(&task.tasks, task.id as isize, next_task_id, ids);
+        }
 
-            id += 1;
+        return ok;
+    }
 
-            let (new_ok, new_id) = DataManager::check_data_integrity_recursive(&task.tasks, id);
-            id = new_id;
-            ok &= new_ok;
+    fn get_storage(&mut self) -> &mut Box<dyn StorageBackend> {
+        if self.storage.is_none() {
+            self.storage = Some(storage::create_storage(&self.config));
         }
 
-        return (ok, id);
+        return self.storage.as_mut().unwrap();
     }
 
     fn load_folding_recursive(&mut self, tasks: &Vec<TaskItem>) {
         for task in tasks {
             if task.folded {
-                DataManager::calculate_folded_hasmap(self, task.id);
+                let position = self.groups[self.selected_group].get_task_position(task.id).unwrap();
+                DataManager::calculate_folded_hasmap(self, position);
             }
             DataManager::load_folding_recursive(self, &task.tasks);
         }
     }
 
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Work has a task with a subtask and two more tasks, one of them completed, Home has a single task
+    fn create_data_manager() -> DataManager {
+        let mut data_manager = DataManager::new();
+
+        let mut work = GroupItem::new(&mut data_manager);
+        work.name = "Work".to_string();
+        let parent = work.add_task("parent".to_string());
+        work.add_subtask("subtask".to_string(), parent);
+        work.add_task("second".to_string());
+        let done = work.add_task("done".to_string());
+        GroupItem::get_task_recursive(done, &mut work.tasks).unwrap().0.set_done(true);
+        data_manager.add_group_item(work);
+
+        let mut home = GroupItem::new(&mut data_manager);
+        home.name = "Home".to_string();
+        home.add_task("errand".to_string());
+        data_manager.add_group_item(home);
+
+        data_manager.changed_groups.clear();
+        return data_manager;
+    }
+
+    fn to_json(data_manager: &DataManager) -> serde_json::Value {
+        return serde_json::to_value(&data_manager.groups).unwrap();
+    }
+
+    // Makes a change the way the interface does, then undoes and redoes it, returns the groups after the change
+    fn check_undo_redo(data_manager: &mut DataManager, change: impl FnOnce(&mut DataManager)) -> serde_json::Value {
+        let before = to_json(data_manager);
+        data_manager.apply();
+        change(data_manager);
+        data_manager.record_change();
+        let after = to_json(data_manager);
+        assert_ne!(before, after);
+
+        data_manager.undo();
+        assert_eq!(to_json(data_manager), before);
+        data_manager.redo();
+        assert_eq!(to_json(data_manager), after);
+
+        return after;
+    }
+
+    #[test]
+    fn undo_and_redo_restore_every_kind_of_change() {
+        let mut data_manager = create_data_manager();
+        let mut states = vec![to_json(&data_manager)];
+
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.get_group(0).add_task("added".to_string()); }));
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.get_group(0).add_subtask("added subtask".to_string(), 0); }));
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.get_group(0).take_task(1); }));
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.get_group(0).move_task(2, true); }));
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.get_group(0).indent_task(4); }));
+        states.push(check_undo_redo(&mut data_manager, |dm| {
+            let task = GroupItem::get_task_recursive(0, &mut dm.get_group(0).tasks).unwrap().0;
+            task.name = "edited".to_string();
+            task.set_done(true);
+            task.fold();
+        }));
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.get_group(0).outdent_task(5); }));
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.get_group(0).archive_task(0); }));
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.get_group(0).restore_archived_task(0); }));
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.move_task_to_group(2, 0, 1, false); }));
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.edit_group_item(1, "Renamed".to_string()); }));
+        states.push(check_undo_redo(&mut data_manager, |dm| {
+            let group = GroupItem::new(dm);
+            dm.add_group_item(group);
+        }));
+        states.push(check_undo_redo(&mut data_manager, |dm| { dm.delete_group_item(0); }));
+
+        for state in states.iter().rev().skip(1) {
+            data_manager.undo();
+            assert_eq!(&to_json(&data_manager), state);
+        }
+
+        for state in states.iter().skip(1) {
+            data_manager.redo();
+            assert_eq!(&to_json(&data_manager), state);
+        }
+    }
+
+    #[test]
+    fn diff_of_an_unchanged_group_is_empty() {
+        let data_manager = create_data_manager();
+        let copies : Vec<GroupItem> = data_manager.groups.clone();
+        let before : Vec<&GroupItem> = copies.iter().collect();
+        let after : Vec<&GroupItem> = data_manager.groups.iter().collect();
+
+        assert!(GroupItem::diff_tasks(before[0], after[0]).unwrap().is_empty());
+        assert!(DataManager::diff_groups(&before, &after).is_empty());
+    }
+}
+
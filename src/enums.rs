@@ -1,14 +1,66 @@
+use serde::{Deserialize, Serialize};
+
 #[derive(Copy, Clone, PartialEq)]
 pub enum FocusedLayout {
     None,
     GroupsLayout,
     TasksLayout,
-    ControlsLayout
+    ControlsLayout,
+    CompletedLayout,
+    HistoryLayout,
+    ArchiveLayout
 }
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum InputMode {
     Navigate,
     Add,
-    Edit
+    Edit,
+    DueDate,
+    TagFilter,
+    Notes,
+    GroupPicker,
+    Confirm
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+    Urgent
+}
+
+
+impl Priority {
+    pub fn raise(&self) -> Priority {
+        match self {
+            Priority::None => Priority::Low,
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            _ => Priority::Urgent
+        }
+    }
+
+    pub fn lower(&self) -> Priority {
+        match self {
+            Priority::Urgent => Priority::High,
+            Priority::High => Priority::Medium,
+            Priority::Medium => Priority::Low,
+            _ => Priority::None
+        }
+    }
+
+    /// Name used by the `priority_<name>_icon` and `priority_<name>_color` keys of settings.ini
+    pub fn config_name(&self) -> &str {
+        match self {
+            Priority::None => "none",
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Urgent => "urgent"
+        }
+    }
 }
\ No newline at end of file
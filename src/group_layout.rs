@@ -12,15 +12,24 @@ use crate::data_manager::GroupItem;
 use crate::enums::InputMode;
 
 pub struct GroupLayout {
-    pub(crate) layout_common: LayoutCommon
+    pub(crate) layout_common: LayoutCommon,
+    delete_question: String
 }
 
 impl GroupLayout {
     pub fn new() -> GroupLayout {
         GroupLayout {
-            layout_common: LayoutCommon::new()
+            layout_common: LayoutCommon::new(),
+            delete_question: String::new()
         }
     }
+
+    fn delete_selected_group(data_manager: &mut DataManager) {
+        data_manager.apply();
+        data_manager.delete_group_item(data_manager.selected_group);
+        let _ = data_manager.save_state();
+        data_manager.selected_group = 0;
+    }
 }
 
 
@@ -54,10 +63,20 @@ impl LayoutCommonTrait for GroupLayout {
                         return;
                     }
 
-                    data_manager.apply();
-                    data_manager.delete_group_item(data_manager.selected_group);
-                    data_manager.save_state();
-                    data_manager.selected_group = 0;
+                    if !data_manager.config.get_bool("group", "ask_before_delete") {
+                        GroupLayout::delete_selected_group(data_manager);
+                        return;
+                    }
+
+                    let group = data_manager.get_group_read_only(data_manager.selected_group);
+                    let tasks_count = group.get_tasks_and_subtasks_count().0;
+                    let tasks = match tasks_count {
+                        0 => String::new(),
+                        1 => " with 1 task".to_string(),
+                        count => format!(" with {} tasks", count)
+                    };
+                    self.delete_question = format!("Delete group '{}'{}? Press '{}' to delete, any other key to cancel", group.name, tasks, data_manager.config.get_key_name("accept_confirmation"));
+                    self.layout_common.input_mode = InputMode::Confirm;
                 } else if data_manager.config.get_key("up_group") == key_code.code {
                     if data_manager.get_group_items().is_empty() {
                         return;
@@ -86,7 +105,7 @@ impl LayoutCommonTrait for GroupLayout {
                     gi.name = self.layout_common.input.drain(..).collect();
                     data_manager.add_group_item(gi);
                     self.layout_common.input_mode = InputMode::Navigate;
-                    data_manager.save_state();
+                    let _ = data_manager.save_state();
                 } else {
                     <GroupLayout as LayoutCommonTrait>::poll_common_keys_input_mode(&key_code, &mut self.layout_common)
                 }
@@ -97,11 +116,18 @@ impl LayoutCommonTrait for GroupLayout {
 
                     data_manager.edit_group_item(data_manager.selected_group, self.layout_common.input.drain(..).collect());
                     self.layout_common.input_mode = InputMode::Navigate;
-                    data_manager.save_state();
+                    let _ = data_manager.save_state();
                 } else {
                     <GroupLayout as LayoutCommonTrait>::poll_common_keys_input_mode(&key_code, &mut self.layout_common)
                 }
-            }
+            },
+            InputMode::Confirm => {
+                if data_manager.config.get_key("accept_confirmation") == key_code.code {
+                    GroupLayout::delete_selected_group(data_manager);
+                }
+                self.layout_common.input_mode = InputMode::Navigate;
+            },
+            _ => {}
         }
     }
 
@@ -137,16 +163,21 @@ impl LayoutCommonTrait for GroupLayout {
             let group_name = app.data_manager.get_group_items()[i].name.as_str();
             line.push_str(group_name);
 
+            let mut default_style = Style::default().remove_modifier(Modifier::BOLD);
+            if app.data_manager.matches_search(group_name) {
+                default_style = default_style.add_modifier(Modifier::REVERSED);
+            }
+
             if i == app.data_manager.selected_group {
                 line = format!("{}{}", app.data_manager.config.group.get("icon").unwrap(), "  ").to_string();
                 line.push_str(group_name);
-                items_list.push(ListItem::new(line).style(Style::default().fg(app.data_manager.config.get_color("group", "selected_color")).remove_modifier(Modifier::BOLD)));
+                items_list.push(ListItem::new(line).style(default_style.fg(app.data_manager.config.get_color("group", "selected_color"))));
                 continue;
             }
 
             line = format!("{}{}", app.data_manager.config.group.get("icon").unwrap(), "  ").to_string();
             line.push_str(group_name);
-            items_list.push(ListItem::new(line).style(Style::default().fg(app.data_manager.config.get_color("group", "non_selected_color")).remove_modifier(Modifier::BOLD)));
+            items_list.push(ListItem::new(line).style(default_style.fg(app.data_manager.config.get_color("group", "non_selected_color"))));
         }
 
         let items = List::new(items_list)
@@ -161,6 +192,11 @@ impl LayoutCommonTrait for GroupLayout {
     }
 
     fn create_and_render_edit_mode<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>) {
+        if app.group_layout.layout_common.input_mode == InputMode::Confirm {
+            <GroupLayout as LayoutCommonTrait>::render_confirmation(f, app.group_layout.delete_question.as_str(), chunk);
+            return;
+        }
+
         let title : String = if app.group_layout.layout_common.input_mode == InputMode::Add { "Add group".to_string() } else { "Edit group".to_string() };
         <GroupLayout as LayoutCommonTrait>::render_common_input_mode(f, &mut app.group_layout.layout_common, title.as_str(), chunk);
     }
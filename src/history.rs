@@ -1,44 +1,282 @@
 use std::collections::VecDeque;
 
+use chrono::{Duration, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use crate::data_manager::{GroupItem, TaskItem};
+
+/// A single change to the data. Groups are referenced by their id and tasks by their id inside the group, places in
+/// the task tree are (parent id or -1, index among its siblings). Edits hold copies of the group or task without
+/// their tasks, everything else is kept whole.
+#[derive(Serialize, Deserialize, Clone)]
+pub enum Operation {
+    AddGroup { index: usize, group: GroupItem },
+    RemoveGroup { index: usize, group: GroupItem },
+    MoveGroup { from: usize, to: usize },
+    EditGroup { before: GroupItem, after: GroupItem },
+    AddTask { group_id: usize, parent: isize, index: usize, task: TaskItem },
+    RemoveTask { group_id: usize, parent: isize, index: usize, task: TaskItem },
+    MoveTask { group_id: usize, task_id: usize, from: (isize, usize), to: (isize, usize) },
+    EditTask { group_id: usize, before: TaskItem, after: TaskItem },
+    AddArchivedTask { group_id: usize, index: usize, task: TaskItem },
+    RemoveArchivedTask { group_id: usize, index: usize, task: TaskItem }
+}
+
+impl Operation {
+    pub fn inverse(&self) -> Operation {
+        return match self {
+            Operation::AddGroup { index, group } => Operation::RemoveGroup { index: *index, group: group.clone() },
+            Operation::RemoveGroup { index, group } => Operation::AddGroup { index: *index, group: group.clone() },
+            Operation::MoveGroup { from, to } => Operation::MoveGroup { from: *to, to: *from },
+            Operation::EditGroup { before, after } => Operation::EditGroup { before: after.clone(), after: before.clone() },
+            Operation::AddTask { group_id, parent, index, task } => Operation::RemoveTask { group_id: *group_id, parent: *parent, index: *index, task: task.clone() },
+            Operation::RemoveTask { group_id, parent, index, task } => Operation::AddTask { group_id: *group_id, parent: *parent, index: *index, task: task.clone() },
+            Operation::MoveTask { group_id, task_id, from, to } => Operation::MoveTask { group_id: *group_id, task_id: *task_id, from: *to, to: *from },
+            Operation::EditTask { group_id, before, after } => Operation::EditTask { group_id: *group_id, before: after.clone(), after: before.clone() },
+            Operation::AddArchivedTask { group_id, index, task } => Operation::RemoveArchivedTask { group_id: *group_id, index: *index, task: task.clone() },
+            Operation::RemoveArchivedTask { group_id, index, task } => Operation::AddArchivedTask { group_id: *group_id, index: *index, task: task.clone() }
+        };
+    }
+    pub fn get_group_id(&self) -> Option<usize> {
+        return match self {
+            Operation::AddGroup { group, .. } | Operation::RemoveGroup { group, .. } => Some(group.get_id()),
+            Operation::MoveGroup { .. } => None,
+            Operation::EditGroup { before, .. } => Some(before.get_id()),
+            Operation::AddTask { group_id, .. } | Operation::RemoveTask { group_id, .. } | Operation::MoveTask { group_id, .. }
+            | Operation::EditTask { group_id, .. } | Operation::AddArchivedTask { group_id, .. } | Operation::RemoveArchivedTask { group_id, .. } => Some(*group_id)
+        };
+    }
+}
+
+/// Everything a single user action changed, undone by applying the inverse of its operations in reverse order.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub(crate) created_at: NaiveDateTime,
+    pub(crate) label: String,
+    pub(crate) operations: Vec<Operation>,
+    pub(crate) next_group_id: (usize, usize),
+    pub(crate) selection_before: (usize, usize),
+    pub(crate) selection_after: (usize, usize)
+}
+
+/// A line of the history file. The file is only appended to, replaying its lines rebuilds the history, and it is
+/// rewritten as a single `Snapshot` line once it has grown well past what the history holds.
+#[derive(Serialize, Deserialize)]
+pub enum HistoryEvent {
+    Apply(HistoryEntry),
+    Undo,
+    Redo,
+    Snapshot { undo_stack: VecDeque<HistoryEntry>, redo_stack: VecDeque<HistoryEntry> },
+    /// Written after the events of every save, `data_hash` is the hash of the data the history ends at. If the data
+    /// file was changed by something else the history no longer applies and is dropped.
+    Saved { data_hash: u64 }
+}
+
 pub struct History {
-    undo_stack: VecDeque<String>,
-    redo_stack: VecDeque<String>
+    undo_stack: VecDeque<HistoryEntry>,
+    redo_stack: VecDeque<HistoryEntry>,
+    limit: usize,
+    max_age_days: i64,
+    unsaved_events: Vec<HistoryEvent>,
+    file_lines: Option<usize>,
+    saved_hash: Option<u64>
 }
 
 impl Default for History {
     fn default() -> Self {
-        Self { undo_stack: Default::default(), redo_stack: Default::default() }
+        Self { undo_stack: Default::default(), redo_stack: Default::default(), limit: 0, max_age_days: 0, unsaved_events: Default::default(), file_lines: None, saved_hash: None }
     }
 }
 
 impl History {
 
-    pub fn new() -> History {
+    pub fn new(limit: usize, max_age_days: i64) -> History {
         History {
             undo_stack: VecDeque::new(),
             redo_stack: VecDeque::new(),
+            limit,
+            max_age_days,
+            unsaved_events: Vec::new(),
+            file_lines: None,
+            saved_hash: None
         }
     }
 
-    pub fn apply(&mut self, current_state: String) {
-        self.undo_stack.push_front(current_state);
+    pub fn apply(&mut self, entry: HistoryEntry) {
+        self.unsaved_events.push(HistoryEvent::Apply(entry.clone()));
+        self.undo_stack.push_front(entry);
         self.redo_stack.clear();
+        self.trim();
+    }
+
+    pub fn undo(&mut self) -> Option<&HistoryEntry> {
+        let entry = self.undo_stack.pop_front()?;
+        self.unsaved_events.push(HistoryEvent::Undo);
+        self.redo_stack.push_front(entry);
+        return self.redo_stack.front();
     }
 
-    pub fn undo(&mut self, current_state: String) -> Result<String, bool> {
-        if self.undo_stack.is_empty() {
-            return Err(true);
+    pub fn redo(&mut self) -> Option<&HistoryEntry> {
+        let entry = self.redo_stack.pop_front()?;
+        self.unsaved_events.push(HistoryEvent::Redo);
+        self.undo_stack.push_front(entry);
+        return self.undo_stack.front();
+    }
+
+    pub fn get_undo_entries(&self) -> &VecDeque<HistoryEntry> {
+        return &self.undo_stack;
+    }
+
+    pub fn get_redo_entries(&self) -> &VecDeque<HistoryEntry> {
+        return &self.redo_stack;
+    }
+
+    /// Rebuilds the history from the lines of the history file. None if a line can't be read or the history doesn't
+    /// end at the data with hash `data_hash`.
+    pub fn load(content: &str, data_hash: u64, limit: usize, max_age_days: i64) -> Option<History> {
+        let mut history = History::new(limit, max_age_days);
+        let mut saved_hash = None;
+        let mut lines = 0;
+
+        for line in content.lines() {
+            match serde_json::from_str::<HistoryEvent>(line).ok()? {
+                HistoryEvent::Apply(entry) => history.apply(entry),
+                HistoryEvent::Undo => { history.undo(); },
+                HistoryEvent::Redo => { history.redo(); },
+                HistoryEvent::Snapshot { undo_stack, redo_stack } => {
+                    history.undo_stack = undo_stack;
+                    history.redo_stack = redo_stack;
+                    history.trim();
+                },
+                HistoryEvent::Saved { data_hash } => saved_hash = Some(data_hash)
+            }
+            lines += 1;
+        }
+
+        if saved_hash != Some(data_hash) {
+            return None;
         }
-        self.redo_stack.push_front(current_state);
-        return Ok(self.undo_stack.pop_front().unwrap());
+
+        history.unsaved_events.clear();
+        history.file_lines = Some(lines);
+        history.saved_hash = saved_hash;
+        return Some(history);
     }
 
-    pub fn redo(&mut self, current_state: String) -> Result<String, bool> {
-        if self.redo_stack.is_empty() {
-            return Err(true);
+    pub fn get_unsaved_lines(&self, data_hash: u64) -> (Vec<String>, bool) {
+        let mut lines : Vec<String> = Vec::new();
+        let rewrite = match self.file_lines {
+            Some(file_lines) => file_lines + self.unsaved_events.len() > 4 * (self.limit + 8),
+            None => true
+        };
+
+        if rewrite {
+            let snapshot = HistoryEvent::Snapshot { undo_stack: self.undo_stack.clone(), redo_stack: self.redo_stack.clone() };
+            lines.extend(serde_json::to_string(&snapshot));
+        } else if self.unsaved_events.is_empty() && self.saved_hash == Some(data_hash) {
+            return (lines, false);
+        } else {
+            lines.extend(self.unsaved_events.iter().filter_map(|event| serde_json::to_string(event).ok()));
         }
-        self.undo_stack.push_front(current_state);
-        return Ok(self.redo_stack.pop_front().unwrap());
+
+        lines.extend(serde_json::to_string(&HistoryEvent::Saved { data_hash }));
+        return (lines, rewrite);
     }
 
-}
\ No newline at end of file
+    pub fn set_saved(&mut self, lines: usize, rewrite: bool, data_hash: u64) {
+        self.unsaved_events.clear();
+        self.file_lines = Some(if rewrite { lines } else { self.file_lines.unwrap_or(0) + lines });
+        self.saved_hash = Some(data_hash);
+    }
+
+    /// Called when the history file couldn't be written, it may hold half a line so it is rewritten next time.
+    pub fn set_file_outdated(&mut self) {
+        self.file_lines = None;
+    }
+
+    fn trim(&mut self) {
+        // Like auto_archive_days, 0 days turns the age limit off
+        if self.max_age_days > 0 {
+            let oldest = Local::now().naive_local() - Duration::days(self.max_age_days);
+            self.undo_stack.retain(|entry| entry.created_at >= oldest);
+            self.redo_stack.retain(|entry| entry.created_at >= oldest);
+        }
+        self.undo_stack.truncate(self.limit);
+        self.redo_stack.truncate(self.limit);
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_manager::DataManager;
+
+    // Work has a task with a completed subtask, a second task and an archived task, Home is empty
+    fn create_groups() -> Vec<GroupItem> {
+        let mut data_manager = DataManager::new();
+        let mut work = GroupItem::new(&mut data_manager);
+        work.name = "Work".to_string();
+        let parent = work.add_task("parent".to_string());
+        let subtask = work.add_subtask("subtask".to_string(), parent);
+        GroupItem::get_task_recursive(subtask, work.get_tasks_mut()).unwrap().0.set_done(true);
+        work.add_task("second".to_string());
+        let archived = work.add_task("archived".to_string());
+        GroupItem::get_task_recursive(archived, work.get_tasks_mut()).unwrap().0.set_done(true);
+        work.archive_task(archived);
+
+        let mut home = GroupItem::new(&mut data_manager);
+        home.name = "Home".to_string();
+
+        return vec![work, home];
+    }
+
+    fn to_json(groups: &Vec<GroupItem>) -> serde_json::Value {
+        return serde_json::to_value(groups).unwrap();
+    }
+
+    #[test]
+    fn inverse_of_every_operation_restores_the_groups() {
+        let groups = create_groups();
+        let work = &groups[0];
+        let work_id = work.get_id();
+        let parent = &work.get_tasks()[0];
+        let subtask = &parent.tasks[0];
+        let second = &work.get_tasks()[1];
+
+        let mut new_group = groups[1].clone();
+        new_group.name = "New".to_string();
+        let mut renamed = work.get_fields();
+        renamed.name = "Renamed".to_string();
+        renamed.sorted_by_priority = true;
+        let mut edited = second.get_fields();
+        edited.name = "edited".to_string();
+        edited.set_done(true);
+        let mut new_subtask = TaskItem::new("new".to_string(), 10, parent.id as isize);
+        new_subtask.notes = "notes".to_string();
+
+        let operations = vec![
+            Operation::AddGroup { index: 1, group: new_group },
+            Operation::RemoveGroup { index: 1, group: groups[1].clone() },
+            Operation::MoveGroup { from: 0, to: 1 },
+            Operation::EditGroup { before: work.get_fields(), after: renamed },
+            Operation::AddTask { group_id: work_id, parent: parent.id as isize, index: 0, task: new_subtask },
+            Operation::RemoveTask { group_id: work_id, parent: parent.id as isize, index: 0, task: subtask.clone() },
+            Operation::RemoveTask { group_id: work_id, parent: -1, index: 0, task: parent.clone() },
+            Operation::MoveTask { group_id: work_id, task_id: second.id, from: (-1, 1), to: (parent.id as isize, 1) },
+            Operation::MoveTask { group_id: work_id, task_id: subtask.id, from: (parent.id as isize, 0), to: (-1, 2) },
+            Operation::EditTask { group_id: work_id, before: second.get_fields(), after: edited },
+            Operation::AddArchivedTask { group_id: work_id, index: 0, task: second.clone() },
+            Operation::RemoveArchivedTask { group_id: work_id, index: 0, task: work.get_archive()[0].clone() }
+        ];
+
+        for operation in &operations {
+            let mut changed = groups.clone();
+            DataManager::apply_operation(&mut changed, operation);
+            assert_ne!(to_json(&changed), to_json(&groups));
+
+            DataManager::apply_operation(&mut changed, &operation.inverse());
+            assert_eq!(to_json(&changed), to_json(&groups));
+        }
+    }
+}
+
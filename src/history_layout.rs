@@ -0,0 +1,94 @@
+use crossterm::event::KeyEvent;
+use tui::backend::Backend;
+use tui::Frame;
+use tui::layout::Rect;
+use tui::style::{Modifier, Style};
+use tui::widgets::{Block, Borders, Clear, List, ListItem, ListState};
+use crate::{App, centered_rect, DataManager, LayoutCommonTrait};
+
+/// Lists the actions that can be undone and redone. The list goes from the furthest redo at the top, through the
+/// current state, to the oldest undo at the bottom.
+pub struct HistoryLayout {
+    selected: usize
+}
+
+impl HistoryLayout {
+    pub fn new() -> HistoryLayout {
+        HistoryLayout {
+            selected: 0
+        }
+    }
+
+    /// Starts with the current state selected.
+    pub fn open(&mut self, data_manager: &DataManager) {
+        self.selected = data_manager.get_history().get_redo_entries().len();
+    }
+}
+
+impl LayoutCommonTrait for HistoryLayout {
+    fn handle_input(&mut self, data_manager: &mut DataManager, key_code: KeyEvent) {
+        let redo_count = data_manager.get_history().get_redo_entries().len();
+        let entries_count = redo_count + 1 + data_manager.get_history().get_undo_entries().len();
+        self.selected = self.selected.min(entries_count - 1);
+
+        if data_manager.config.get_key("up_task_or_subtask") == key_code.code {
+            self.selected = self.selected.saturating_sub(1);
+        } else if data_manager.config.get_key("down_task_or_subtask") == key_code.code {
+            self.selected = (self.selected + 1).min(entries_count - 1);
+        } else if data_manager.config.get_key("jump_in_history") == key_code.code {
+            // An undo entry is reverted together with everything done after it, a redo entry is done again
+            // together with everything undone before it
+            if self.selected < redo_count {
+                data_manager.redo_steps(redo_count - self.selected);
+            } else if self.selected > redo_count {
+                data_manager.undo_steps(self.selected - redo_count);
+            }
+            let _ = data_manager.save_state();
+
+            self.open(data_manager);
+        }
+    }
+
+    fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>, frame_size: &Rect) {
+        HistoryLayout::create_and_render_item_list(f, app, chunk, frame_size);
+    }
+
+    fn create_and_render_base_block<B: Backend>(_f: &mut Frame<B>, _app: &mut App, _chunk: &Vec<Rect>) {  }
+
+    fn create_and_render_item_list<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>, _frame_size: &Rect) {
+        let area = centered_rect(70, 80, chunk[1]);
+        let history = app.data_manager.get_history();
+        let selected_color = app.data_manager.config.get_color("task", "selected_color");
+        let non_selected_color = app.data_manager.config.get_color("task", "non_selected_color");
+        let completed_color = app.data_manager.config.get_color("task", "completed_color");
+
+        let mut lines : Vec<(String, Style)> = Vec::new();
+        for entry in history.get_redo_entries().iter().rev() {
+            lines.push((format!("  {}  {} (undone)", entry.created_at.format("%Y-%m-%d %H:%M:%S"), entry.label), Style::default().fg(non_selected_color).add_modifier(Modifier::DIM)));
+        }
+        lines.push(("● Current state".to_string(), Style::default().fg(completed_color)));
+        for entry in history.get_undo_entries() {
+            lines.push((format!("  {}  {}", entry.created_at.format("%Y-%m-%d %H:%M:%S"), entry.label), Style::default().fg(non_selected_color)));
+        }
+
+        let selected = app.history_layout.selected.min(lines.len() - 1);
+        let items_list : Vec<ListItem> = lines.into_iter().enumerate().map(|(i, (line, style))| {
+            if i == selected {
+                return ListItem::new(line).style(style.fg(selected_color).add_modifier(Modifier::BOLD));
+            }
+            return ListItem::new(line).style(style);
+        }).collect();
+
+        let items = List::new(items_list)
+            .block(Block::default().title("Undo history (Enter to go back to an entry)").borders(Borders::ALL));
+
+        // The state only scrolls the list so the selected entry is always visible
+        let mut state = ListState::default();
+        state.select(Some(selected));
+
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(items, area, &mut state);
+    }
+
+    fn create_and_render_edit_mode<B: Backend>(_f: &mut Frame<B>, _app: &mut App, _chunk: &Vec<Rect>) {  }
+}
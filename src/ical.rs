@@ -0,0 +1,95 @@
+use chrono::Local;
+
+use crate::data_manager::{GroupItem, TaskItem};
+use crate::enums::Priority;
+use crate::import::to_utc_date;
+
+/// An iCalendar file (RFC 5545) with a VTODO for every task, so calendar and todo apps can show the tasks. Subtasks
+/// point to their parent with RELATED-TO and the group is the category. The UIDs come from the group and task ids,
+/// so subscribing apps see the same tasks after exporting again.
+pub fn export_groups(groups: &Vec<&GroupItem>) -> String {
+    let mut lines : Vec<String> = vec!["BEGIN:VCALENDAR".to_string(), "VERSION:2.0".to_string(), "PRODID:-//term_do//term_do//EN".to_string()];
+    let now = to_utc_date(Local::now().naive_local());
+
+    for group in groups {
+        export_tasks_recursive(group, group.get_tasks(), None, &now, &mut lines);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    return lines.iter().map(|line| fold_line(line)).collect::<Vec<String>>().join("\r\n") + "\r\n";
+}
+
+fn export_tasks_recursive(group: &GroupItem, tasks: &Vec<TaskItem>, parent_uid: Option<&str>, now: &str, lines: &mut Vec<String>) {
+    for task in tasks {
+        let uid = format!("{}-{}-{}@term_do", group.get_id(), task.id, task.created_at.map(|created_at| created_at.and_utc().timestamp()).unwrap_or(0));
+
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}", uid));
+        lines.push(format!("DTSTAMP:{}", now));
+        lines.push(format!("SUMMARY:{}", escape_text(&task.name)));
+        lines.push(format!("CATEGORIES:{}", escape_text(&group.name)));
+        if !task.notes.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape_text(&task.notes)));
+        }
+        if let Some(created_at) = task.created_at {
+            lines.push(format!("CREATED:{}", to_utc_date(created_at)));
+        }
+        if let Some(edited_at) = task.edited_at {
+            lines.push(format!("LAST-MODIFIED:{}", to_utc_date(edited_at)));
+        }
+
+        if task.done {
+            lines.push("STATUS:COMPLETED".to_string());
+            if let Some(completed_at) = task.completed_at {
+                lines.push(format!("COMPLETED:{}", to_utc_date(completed_at)));
+            }
+        } else {
+            lines.push("STATUS:NEEDS-ACTION".to_string());
+        }
+
+        match (task.due_date, task.due_time) {
+            (Some(due_date), Some(due_time)) => lines.push(format!("DUE:{}", to_utc_date(due_date.and_time(due_time)))),
+            (Some(due_date), None) => lines.push(format!("DUE;VALUE=DATE:{}", due_date.format("%Y%m%d"))),
+            _ => {}
+        }
+
+        // 1 is the highest priority and 9 the lowest
+        let priority = match task.priority {
+            Priority::Urgent => Some(1),
+            Priority::High => Some(3),
+            Priority::Medium => Some(5),
+            Priority::Low => Some(9),
+            Priority::None => None
+        };
+        if let Some(priority) = priority {
+            lines.push(format!("PRIORITY:{}", priority));
+        }
+
+        if let Some(parent_uid) = parent_uid {
+            lines.push(format!("RELATED-TO;RELTYPE=PARENT:{}", parent_uid));
+        }
+        lines.push("END:VTODO".to_string());
+
+        export_tasks_recursive(group, &task.tasks, Some(&uid), now, lines);
+    }
+}
+
+fn escape_text(text: &str) -> String {
+    return text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace("\r\n", "\\n").replace(['\n', '\r'], "\\n");
+}
+
+/// Lines longer than 75 bytes are split, the following parts starting with a space, without splitting a character.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+
+    return folded;
+}
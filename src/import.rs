@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+
+use crate::data_manager::{DataManager, GroupItem, TaskItem};
+
+/// A task read from another format. `key` is the id the format gives it and `parent` the key of its parent task, so
+/// subtasks can be linked to their parent wherever they are in the file.
+pub struct ImportedTask {
+    pub(crate) group: String,
+    pub(crate) key: Option<String>,
+    pub(crate) parent: Option<String>,
+    /// Everything but the place in the tree and the id, which are given when the task is added.
+    pub(crate) fields: TaskItem
+}
+
+/// Adds the tasks to the groups with their names, created if there are none, keeping their order. Parents are added
+/// before their subtasks, and tasks whose parent isn't among them (or that end up in a loop of parents) become top
+/// level tasks. Returns how many tasks were added.
+pub fn add_imported_tasks(data_manager: &mut DataManager, tasks: Vec<ImportedTask>) -> usize {
+    let mut groups : Vec<String> = Vec::new();
+    let mut tasks_by_group : HashMap<String, Vec<ImportedTask>> = HashMap::new();
+    for task in tasks {
+        if !groups.contains(&task.group) {
+            groups.push(task.group.clone());
+        }
+        tasks_by_group.entry(task.group.clone()).or_default().push(task);
+    }
+
+    let mut imported = 0;
+    for group in groups {
+        let tasks = tasks_by_group.remove(&group).unwrap();
+        let index = data_manager.find_or_add_group(&group);
+        let gi = data_manager.get_group(index);
+
+        let keys : HashMap<&String, usize> = tasks.iter().enumerate().filter_map(|(i, task)| task.key.as_ref().map(|key| (key, i))).collect();
+        let parent_of = |task: &ImportedTask| task.parent.as_ref().and_then(|parent| keys.get(parent)).copied();
+
+        let mut children : HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, task) in tasks.iter().enumerate() {
+            if let Some(parent) = parent_of(task) {
+                children.entry(parent).or_default().push(i);
+            }
+        }
+
+        let mut added : Vec<bool> = vec![false; tasks.len()];
+        for only_top_level in [true, false] {
+            for i in 0..tasks.len() {
+                if !added[i] && (!only_top_level || parent_of(&tasks[i]).is_none()) {
+                    add_task_recursive(gi, &tasks, &children, i, None, &mut added);
+                }
+            }
+        }
+
+        imported += tasks.len();
+    }
+
+    return imported;
+}
+
+fn add_task_recursive(gi: &mut GroupItem, tasks: &Vec<ImportedTask>, children: &HashMap<usize, Vec<usize>>, i: usize, parent_id: Option<usize>, added: &mut Vec<bool>) {
+    added[i] = true;
+
+    let mut fields = tasks[i].fields.clone();
+    fields.id = match parent_id {
+        Some(parent_id) => gi.add_subtask(fields.name.clone(), parent_id),
+        None => gi.add_task(fields.name.clone())
+    };
+    gi.set_task_fields(&fields);
+
+    for child in children.get(&i).unwrap_or(&Vec::new()) {
+        if !added[*child] {
+            add_task_recursive(gi, tasks, children, *child, Some(fields.id), added);
+        }
+    }
+}
+
+/// Dates like 20261020T153000Z, in UTC as Taskwarrior and iCalendar write them. The dates of the data file are local.
+const UTC_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+pub fn to_utc_date(date: NaiveDateTime) -> String {
+    return match Local.from_local_datetime(&date).earliest() {
+        Some(local) => local.with_timezone(&Utc).format(UTC_DATE_FORMAT).to_string(),
+        None => date.format(UTC_DATE_FORMAT).to_string()
+    };
+}
+
+pub fn from_utc_date(date: &str) -> Option<NaiveDateTime> {
+    let utc = NaiveDateTime::parse_from_str(date, UTC_DATE_FORMAT).ok()?;
+    return Some(Utc.from_utc_datetime(&utc).with_timezone(&Local).naive_local());
+}
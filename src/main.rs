@@ -5,8 +5,22 @@ mod data_manager;
 mod history;
 mod controls_layout;
 mod config_manager;
-
-use std::{env, error::Error, fs, io};
+mod search_layout;
+mod completed_layout;
+mod migrations;
+mod history_layout;
+mod archive_layout;
+mod cli;
+mod csv;
+mod ical;
+mod import;
+mod markdown;
+mod org;
+mod todo_txt;
+mod taskwarrior;
+mod storage;
+
+use std::{env, error::Error, fs, io, panic};
 use std::collections::VecDeque;
 use std::fs::File;
 use tui::{
@@ -21,13 +35,18 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use tui::style::{Modifier, Style};
-use tui::widgets::Clear;
+use tui::text::{Span, Spans};
+use tui::widgets::{Clear, Wrap};
 
 use crate::data_manager::{DataManager, LayoutCommon};
 use crate::enums::{FocusedLayout, InputMode};
 use crate::group_layout::GroupLayout;
 use crate::tasks_layout::{TaskLayout};
 use crate::controls_layout::ControlsLayout;
+use crate::search_layout::SearchLayout;
+use crate::completed_layout::CompletedLayout;
+use crate::history_layout::HistoryLayout;
+use crate::archive_layout::ArchiveLayout;
 
 use unicode_width::UnicodeWidthStr;
 use crate::config_manager::ConfigManager;
@@ -68,6 +87,43 @@ trait LayoutCommonTrait {
         }
     }
 
+    /// Asks before doing something that removes data, like deleting a group or a task.
+    fn render_confirmation<B: Backend>(f: &mut Frame<B>, question: &str, chunk: &Vec<Rect>) {
+        let options_block = Block::default().title("Confirm").borders(Borders::ALL);
+        let area = centered_rect(40, 20, chunk[1]);
+
+        let text = Paragraph::new(question)
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .wrap(Wrap { trim: true })
+            .block(options_block);
+
+        f.render_widget(Clear, area);
+        f.render_widget(text, area);
+    }
+
+    fn render_multiline_input_mode<B: Backend>(f: &mut Frame<B>, layout_common: &mut LayoutCommon, title: &str, chunk: &Vec<Rect>) {
+        if layout_common.is_in_edit_mode() {
+            let options_block = Block::default().title(title).borders(Borders::ALL);
+            let area = centered_rect(60, 50, chunk[1]);
+
+            let input = Paragraph::new(layout_common.input.as_str())
+                .style(Style::default().add_modifier(Modifier::BOLD))
+                .block(options_block);
+
+            f.render_widget(Clear, area);
+            f.render_widget(input, area);
+
+            let before_cursor : String = layout_common.input.chars().take(layout_common.cursor_pos).collect();
+            let line = before_cursor.matches('\n').count();
+            let column = before_cursor.rsplit('\n').next().unwrap_or("").width();
+
+            f.set_cursor(
+                area.x + column as u16 + 1,
+                area.y + line as u16 + 1,
+            )
+        }
+    }
+
     fn poll_common_keys_input_mode(key_code: &event::KeyEvent, layout_common: &mut LayoutCommon) {
         match key_code.code {
             KeyCode::Char(c) => {
@@ -92,6 +148,44 @@ trait LayoutCommonTrait {
         }
     }
 
+    /// Multiline inputs keep `cursor_pos` as a char index, as a new line has no width.
+    fn poll_multiline_keys_input_mode(key_code: &event::KeyEvent, layout_common: &mut LayoutCommon) {
+        let length = layout_common.input.chars().count();
+        let byte_pos = |input: &String, pos: usize| input.char_indices().nth(pos).map(|(i, _)| i).unwrap_or(input.len());
+
+        match key_code.code {
+            KeyCode::Char(c) => {
+                let pos = byte_pos(&layout_common.input, layout_common.cursor_pos);
+                layout_common.input.insert(pos, c);
+                layout_common.cursor_pos += 1;
+            },
+            KeyCode::Enter => {
+                let pos = byte_pos(&layout_common.input, layout_common.cursor_pos);
+                layout_common.input.insert(pos, '\n');
+                layout_common.cursor_pos += 1;
+            },
+            KeyCode::Backspace if layout_common.cursor_pos > 0 => {
+                let pos = byte_pos(&layout_common.input, layout_common.cursor_pos - 1);
+                layout_common.input.remove(pos);
+                layout_common.cursor_pos -= 1;
+            },
+            KeyCode::Delete if layout_common.cursor_pos < length => {
+                let pos = byte_pos(&layout_common.input, layout_common.cursor_pos);
+                layout_common.input.remove(pos);
+            },
+            KeyCode::Left if layout_common.cursor_pos > 0 => {
+                layout_common.cursor_pos -= 1;
+            },
+            KeyCode::Right if layout_common.cursor_pos < length => {
+                layout_common.cursor_pos += 1;
+            },
+            KeyCode::Esc => {
+                <TaskLayout as LayoutCommonTrait>::esc_key_input_mode(layout_common);
+            },
+            _ => {}
+        }
+    }
+
     fn left_key_input_mode(layout_common: &mut LayoutCommon) {
         if layout_common.cursor_pos - layout_common.starting_rendering_input_point > 0 {
             layout_common.cursor_pos -= 1;
@@ -161,6 +255,10 @@ struct App {
     group_layout: GroupLayout,
     task_layout: TaskLayout,
     controls_layout: ControlsLayout,
+    search_layout: SearchLayout,
+    completed_layout: CompletedLayout,
+    history_layout: HistoryLayout,
+    archive_layout: ArchiveLayout,
     run: bool,
     data_manager: DataManager
 }
@@ -173,6 +271,10 @@ impl App {
             group_layout: GroupLayout::new(),
             task_layout: TaskLayout::new(),
             controls_layout: ControlsLayout::new(),
+            search_layout: SearchLayout::new(),
+            completed_layout: CompletedLayout::new(),
+            history_layout: HistoryLayout::new(),
+            archive_layout: ArchiveLayout::new(),
             run: true,
             data_manager: DataManager::new()
         }
@@ -184,7 +286,20 @@ impl App {
     }
 
     pub fn is_in_edit_mode(&self) -> bool {
-        <GroupLayout as LayoutCommonTrait>::is_in_edit_mode(&self.group_layout.layout_common) || <TaskLayout as LayoutCommonTrait>::is_in_edit_mode(&self.task_layout.layout_common)
+        <GroupLayout as LayoutCommonTrait>::is_in_edit_mode(&self.group_layout.layout_common) || <TaskLayout as LayoutCommonTrait>::is_in_edit_mode(&self.task_layout.layout_common) ||
+            <SearchLayout as LayoutCommonTrait>::is_in_edit_mode(&self.search_layout.layout_common)
+    }
+
+    pub fn is_in_main_layouts(&self) -> bool {
+        self.focused_layout == FocusedLayout::GroupsLayout || self.focused_layout == FocusedLayout::TasksLayout
+    }
+
+    pub fn focus_search_match(&mut self) {
+        match self.search_layout.last_match {
+            Some(true) => self.update_state(FocusedLayout::TasksLayout),
+            Some(false) => self.update_state(FocusedLayout::GroupsLayout),
+            None => {}
+        }
     }
 }
 
@@ -193,14 +308,27 @@ fn handle_command_line_mode(args: &mut VecDeque<String>) {
     let default = config.default_settings_file;
 
     while !args.is_empty() {
-        let command_opt = args.pop_front();
-        let value_opt = args.pop_front();
+        let command = args.pop_front().unwrap();
+
+        if command.eq("--list-backups") {
+            let mut data_manager = DataManager::new();
+            let backups = data_manager.get_backups();
+            if backups.is_empty() {
+                println!("There are no backups of {}", data_manager.get_data_file_path());
+            }
+
+            for (number, modified, size) in backups {
+                let modified : chrono::DateTime<chrono::Local> = modified.into();
+                println!("{}\t{}\t{} bytes", number, modified.format("%Y-%m-%d %H:%M:%S"), size);
+            }
+            continue;
+        }
 
-        if command_opt.is_none() || value_opt.is_none() {
+        let value_opt = args.pop_front();
+        if value_opt.is_none() {
             break;
         }
 
-        let command = command_opt.unwrap();
         let value = value_opt.unwrap();
 
         if command.eq("--set-settings-path") {
@@ -210,10 +338,60 @@ fn handle_command_line_mode(args: &mut VecDeque<String>) {
             config.ini.with_section(Some("paths")).set("data_path", value.as_str());
             config.ini.write_to_file("settings.ini").expect("Couldn't write new config to settings.ini");
         } else if command.eq("--create-data-in-path") {
-            File::create(format!("{}/{}", value, "data.json")).expect(format!("Couldn't create the file data.json at {}", value).as_str());
-            let data_manager = DataManager::new();
-            let content = serde_json::to_string_pretty(&data_manager).unwrap();
-            fs::write(format!("{}/{}", value, "data.json"), content).expect(format!("Couldn't write the file data.json at {}", value).as_str());
+            let mut data_manager = DataManager::new();
+            match data_manager.create_data_file(&value) {
+                Ok(data_file) => println!("Created {}", data_file),
+                Err(error) => eprintln!("{}", error)
+            }
+        } else if command.eq("--restore-backup") {
+            let mut data_manager = DataManager::new();
+            match value.parse::<usize>() {
+                Ok(number) => match data_manager.restore_backup(number) {
+                    Ok(_) => println!("Restored backup {} into {}", number, data_manager.get_data_file_path()),
+                    Err(error) => eprintln!("{}", error)
+                },
+                Err(_) => eprintln!("The backup to restore must be a number, use --list-backups to see them")
+            }
+        } else if command.eq("--export-markdown") {
+            if let Err(error) = cli::export(&value, args.pop_front(), markdown::export_groups) {
+                eprintln!("{}", error);
+            }
+        } else if command.eq("--import-markdown") {
+            if let Err(error) = cli::import(&value, markdown::import) {
+                eprintln!("{}", error);
+            }
+        } else if command.eq("--export-todo-txt") {
+            if let Err(error) = cli::export(&value, args.pop_front(), todo_txt::export_groups) {
+                eprintln!("{}", error);
+            }
+        } else if command.eq("--import-todo-txt") {
+            if let Err(error) = cli::import(&value, todo_txt::import) {
+                eprintln!("{}", error);
+            }
+        } else if command.eq("--export-taskwarrior") {
+            if let Err(error) = cli::export(&value, args.pop_front(), taskwarrior::export_groups) {
+                eprintln!("{}", error);
+            }
+        } else if command.eq("--import-taskwarrior") {
+            if let Err(error) = cli::import(&value, taskwarrior::import) {
+                eprintln!("{}", error);
+            }
+        } else if command.eq("--export-csv") {
+            if let Err(error) = cli::export(&value, args.pop_front(), csv::export_groups) {
+                eprintln!("{}", error);
+            }
+        } else if command.eq("--export-ical") {
+            if let Err(error) = cli::export(&value, args.pop_front(), ical::export_groups) {
+                eprintln!("{}", error);
+            }
+        } else if command.eq("--export-org") {
+            if let Err(error) = cli::export(&value, args.pop_front(), org::export_groups) {
+                eprintln!("{}", error);
+            }
+        } else if command.eq("--import-org") {
+            if let Err(error) = cli::import(&value, org::import) {
+                eprintln!("{}", error);
+            }
         } else if command.eq("--create-settings-in-path") {
             File::create(format!("{}/{}", value, "settings.ini")).expect(format!("Couldn't create the file settings.ini at {}", value).as_str());
             fs::write(format!("{}/{}", value, "settings.ini"), &default).expect(format!("Couldn't write the file settings.ini at {}", value).as_str());
@@ -225,27 +403,44 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut args: VecDeque<String> = env::args().collect();
     if args.len() > 1 {
         args.pop_front();
+
+        if cli::SUBCOMMANDS.contains(&args[0].as_str()) {
+            if let Err(error) = cli::run_subcommand(&mut args) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+
         handle_command_line_mode(&mut args);
         return Ok(());
     }
 
+    // Whatever happens the terminal must not be left in raw mode
+    let default_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_panic_hook(info);
+    }));
+
+    let mut app = App::new();
+    let load_result = app.data_manager.load_state();
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new();
-    app.data_manager.load_state();
-    let res =  { run_app(&mut terminal, &mut app) };
+    let res = match load_result {
+        Ok(_) => run_app(&mut terminal, &mut app),
+        Err(error) => match run_recovery(&mut terminal, &mut app, error) {
+            Ok(true) => run_app(&mut terminal, &mut app),
+            other => other.map(|_| ())
+        }
+    };
 
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    restore_terminal();
 
     if let Err(err) = res {
         println!("{:?}", err)
@@ -260,9 +455,17 @@ fn main() -> Result<(), Box<dyn Error>> {
         terminal.draw(|f| ui(f, app))?;
 
         if let Event::Key(key) = event::read()? {
+            app.data_manager.status_message = None;
+
+            if <SearchLayout as LayoutCommonTrait>::is_in_edit_mode(&app.search_layout.layout_common) {
+                app.search_layout.handle_input(&mut app.data_manager, key);
+                app.focus_search_match();
+                continue;
+            }
 
             if key.code == KeyCode::Char('q') || key.code == KeyCode::Esc {
-                if app.focused_layout == FocusedLayout::ControlsLayout {
+                if app.focused_layout == FocusedLayout::ControlsLayout || app.focused_layout == FocusedLayout::CompletedLayout || app.focused_layout == FocusedLayout::HistoryLayout ||
+                    app.focused_layout == FocusedLayout::ArchiveLayout {
                     app.update_state(FocusedLayout::GroupsLayout);
                 } else {
                     if  !app.is_in_edit_mode() {
@@ -276,10 +479,42 @@ fn main() -> Result<(), Box<dyn Error>> {
                 app.update_state(FocusedLayout::TasksLayout);
             } else if app.data_manager.config.get_key("undo") == key.code && !app.is_in_edit_mode() {
                 app.data_manager.undo();
-                app.data_manager.save_state();
+                let _ = app.data_manager.save_state();
             } else if app.data_manager.config.get_key("redo") == key.code && !app.is_in_edit_mode() {
                 app.data_manager.redo();
-                app.data_manager.save_state();
+                let _ = app.data_manager.save_state();
+            } else if app.data_manager.config.get_key("search") == key.code && !app.is_in_edit_mode() && app.is_in_main_layouts() {
+                app.search_layout.open(&mut app.data_manager, app.focused_layout);
+                continue;
+            } else if (app.data_manager.config.get_key("next_search_match") == key.code || app.data_manager.config.get_key("previous_search_match") == key.code) &&
+                !app.is_in_edit_mode() && !app.data_manager.search_query.is_empty() && app.is_in_main_layouts() {
+                let forward = app.data_manager.config.get_key("next_search_match") == key.code;
+                app.search_layout.jump(&mut app.data_manager, app.focused_layout, forward);
+                app.focus_search_match();
+                continue;
+            } else if app.data_manager.config.get_key("recently_completed") == key.code && !app.is_in_edit_mode() {
+                if app.focused_layout == FocusedLayout::CompletedLayout {
+                    app.update_state(FocusedLayout::GroupsLayout);
+                } else {
+                    app.update_state(FocusedLayout::CompletedLayout);
+                }
+                continue;
+            } else if app.data_manager.config.get_key("show_archive") == key.code && !app.is_in_edit_mode() {
+                if app.focused_layout == FocusedLayout::ArchiveLayout {
+                    app.update_state(FocusedLayout::TasksLayout);
+                } else {
+                    app.archive_layout.open();
+                    app.update_state(FocusedLayout::ArchiveLayout);
+                }
+                continue;
+            } else if app.data_manager.config.get_key("undo_history") == key.code && !app.is_in_edit_mode() {
+                if app.focused_layout == FocusedLayout::HistoryLayout {
+                    app.update_state(FocusedLayout::GroupsLayout);
+                } else {
+                    app.history_layout.open(&app.data_manager);
+                    app.update_state(FocusedLayout::HistoryLayout);
+                }
+                continue;
             } else if key.code == KeyCode::Char('?') && !app.is_in_edit_mode() {
                 if app.focused_layout == FocusedLayout::ControlsLayout {
                     app.update_state(FocusedLayout::GroupsLayout);
@@ -298,6 +533,15 @@ fn main() -> Result<(), Box<dyn Error>> {
                 FocusedLayout::ControlsLayout => {
                     app.controls_layout.handle_input(&mut app.data_manager,key);
                 },
+                FocusedLayout::CompletedLayout => {
+                    app.completed_layout.handle_input(&mut app.data_manager,key);
+                },
+                FocusedLayout::HistoryLayout => {
+                    app.history_layout.handle_input(&mut app.data_manager,key);
+                },
+                FocusedLayout::ArchiveLayout => {
+                    app.archive_layout.handle_input(&mut app.data_manager,key);
+                },
                 _ => {}
             }
         }
@@ -316,7 +560,13 @@ fn main() -> Result<(), Box<dyn Error>> {
     let top_block = Block::default()
         .borders(Borders::ALL);
 
-    let title = Paragraph::new("Term-do \n press ? to see controls").block(top_block).alignment(Alignment::Center);
+    // The second line works as a status line, a failed save is shown there until a save works again
+    let status = match (app.data_manager.get_save_error(), &app.data_manager.status_message) {
+        (Some(error), _) => Spans::from(Span::styled(error.clone(), Style::default().fg(app.data_manager.config.get_color("task", "overdue_color")))),
+        (None, Some(message)) => Spans::from(message.clone()),
+        (None, None) => Spans::from(" press ? to see controls")
+    };
+    let title = Paragraph::new(vec![Spans::from("Term-do "), status]).block(top_block).alignment(Alignment::Center);
     f.render_widget(title, chunks[0]);
 
     let lower_chunks = Layout::default()
@@ -324,17 +574,84 @@ fn main() -> Result<(), Box<dyn Error>> {
         .direction(Direction::Horizontal)
         .split(chunks[1]);
 
-    if app.focused_layout != FocusedLayout::ControlsLayout {
+    if app.focused_layout == FocusedLayout::CompletedLayout {
+        <CompletedLayout as LayoutCommonTrait>::ui(f, app, &chunks, &f.size());
+    } else if app.focused_layout == FocusedLayout::ArchiveLayout {
+        <ArchiveLayout as LayoutCommonTrait>::ui(f, app, &chunks, &f.size());
+    } else if app.focused_layout != FocusedLayout::ControlsLayout {
         <GroupLayout as LayoutCommonTrait>::ui(f, app, &lower_chunks, &f.size());
         <TaskLayout as LayoutCommonTrait>::ui(f, app, &lower_chunks, &f.size());
 
         <GroupLayout as LayoutCommonTrait>::create_and_render_edit_mode(f, app, &lower_chunks);
         <TaskLayout as LayoutCommonTrait>::create_and_render_edit_mode(f, app, &lower_chunks);
+        <SearchLayout as LayoutCommonTrait>::create_and_render_edit_mode(f, app, &lower_chunks);
+
+        if app.focused_layout == FocusedLayout::HistoryLayout {
+            <HistoryLayout as LayoutCommonTrait>::ui(f, app, &chunks, &f.size());
+        }
     } else {
         <ControlsLayout as LayoutCommonTrait>::ui(f, app, &chunks, &f.size());
     }
 }
 
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, crossterm::cursor::Show);
+}
+
+/// Shown when data.json can't be loaded, returns false if the user chooses to quit.
+fn run_recovery<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, error: String) -> io::Result<bool> {
+    let mut message = String::new();
+
+    loop {
+        terminal.draw(|f| recovery_ui(f, error.as_str(), message.as_str()))?;
+
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Char('b') => {
+                    match app.data_manager.load_latest_backup() {
+                        Ok(_) => match app.data_manager.move_data_file_aside().and_then(|_| app.data_manager.save_state()) {
+                            Ok(_) => return Ok(true),
+                            Err(save_error) => message = save_error
+                        },
+                        Err(backup_error) => message = backup_error
+                    }
+                },
+                KeyCode::Char('e') => {
+                    match app.data_manager.move_data_file_aside().and_then(|_| app.data_manager.save_state()) {
+                        Ok(_) => return Ok(true),
+                        Err(save_error) => message = save_error
+                    }
+                },
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn recovery_ui<B: Backend>(f: &mut Frame<B>, error: &str, message: &str) {
+    let text = vec![
+        Spans::from(""),
+        Spans::from("The data file couldn't be loaded:"),
+        Spans::from(""),
+        Spans::from(error.to_string()),
+        Spans::from(""),
+        Spans::from("'b' to open the latest valid backup (the broken file is kept aside)"),
+        Spans::from("'e' to start with no data (the broken file is kept aside)"),
+        Spans::from("'q' to quit without touching anything"),
+        Spans::from(""),
+        Spans::from(message.to_string()),
+    ];
+
+    let block = Block::default()
+        .title("Term-do recovery")
+        .borders(Borders::ALL);
+
+    let p = Paragraph::new(text).block(block).alignment(Alignment::Center).wrap(Wrap { trim: true });
+    f.render_widget(p, centered_rect(80, 60, f.size()));
+}
+
 /// helper function to create a centered rect using up certain percentage of the available rect `r`
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
@@ -0,0 +1,107 @@
+use crate::data_manager::{DataManager, GroupItem, TaskItem};
+
+/// Every group becomes a heading followed by its tasks as a nested checklist, the way task lists are written in pull
+/// request descriptions and wiki pages.
+pub fn export_groups(groups: &Vec<&GroupItem>) -> String {
+    let mut markdown = String::new();
+    for group in groups {
+        if !markdown.is_empty() {
+            markdown.push('\n');
+        }
+
+        markdown.push_str(&format!("# {}\n\n", group.name));
+        export_tasks_recursive(group.get_tasks(), 0, &mut markdown);
+    }
+
+    return markdown;
+}
+
+fn export_tasks_recursive(tasks: &Vec<TaskItem>, depth: usize, markdown: &mut String) {
+    for task in tasks {
+        markdown.push_str(&format!("{}- [{}] {}\n", "  ".repeat(depth), if task.done { "x" } else { " " }, task.name));
+        export_tasks_recursive(&task.tasks, depth + 1, markdown);
+    }
+}
+
+/// Adds the checklist items of a markdown text as new tasks, nested as they are indented. Items under a heading go
+/// to the group with that name, which is created if there is none, and items before the first heading go to
+/// `default_group`. Anything that isn't a heading or a `- [ ]` / `- [x]` item is ignored.
+/// Returns how many tasks were added.
+pub fn import(data_manager: &mut DataManager, content: &str, default_group: &str) -> Result<usize, String> {
+    let mut imported = 0;
+    let mut group_index : Option<usize> = None;
+    // Indentation width and id of the items that can still get subtasks, from the outermost one
+    let mut parents : Vec<(usize, usize)> = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+
+        if let Some(heading) = parse_heading(trimmed) {
+            group_index = Some(data_manager.find_or_add_group(heading));
+            parents.clear();
+            continue;
+        }
+
+        let (done, name) = match parse_item(trimmed) {
+            Some(item) => item,
+            None => continue
+        };
+
+        let width = line[..line.len() - trimmed.len()].chars().map(|c| if c == '\t' { 4 } else { 1 }).sum::<usize>();
+        while let Some(parent) = parents.last() {
+            if parent.0 < width {
+                break;
+            }
+            parents.pop();
+        }
+
+        let index = *group_index.get_or_insert_with(|| data_manager.find_or_add_group(default_group));
+        let gi = data_manager.get_group(index);
+        let task_id = match parents.last() {
+            Some((_, parent_id)) => gi.add_subtask(name.to_string(), *parent_id),
+            None => gi.add_task(name.to_string())
+        };
+        if done {
+            GroupItem::get_task_recursive(task_id, gi.get_tasks_mut()).unwrap().0.set_done(true);
+        }
+
+        parents.push((width, task_id));
+        imported += 1;
+    }
+
+    return Ok(imported);
+}
+
+fn parse_heading(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('#');
+    if text.len() == line.len() || !(text.is_empty() || text.starts_with(char::is_whitespace)) {
+        return None;
+    }
+
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    return Some(text);
+}
+
+/// (done, name) of a `- [ ] name` item, `*` and `+` bullets work too.
+fn parse_item(line: &str) -> Option<(bool, &str)> {
+    let item = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).or_else(|| line.strip_prefix("+ "))?.trim_start();
+
+    let done = if item.starts_with("[ ]") {
+        false
+    } else if item.starts_with("[x]") || item.starts_with("[X]") {
+        true
+    } else {
+        return None;
+    };
+
+    let name = item[3..].trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    return Some((done, name));
+}
@@ -0,0 +1,225 @@
+use serde_json::{Map, Value};
+
+/// Version of the data.json schema written by this build, bump it and add a step to `MIGRATIONS` whenever
+/// a field is added, removed or changes meaning in `DataManager`, `GroupItem` or `TaskItem`.
+pub const CURRENT_DATA_VERSION: u64 = 2;
+
+/// `MIGRATIONS[n]` upgrades a file from version n to version n + 1.
+const MIGRATIONS: [fn(&mut Map<String, Value>); CURRENT_DATA_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2
+];
+
+pub fn get_data_version(data: &Value) -> u64 {
+    return data.get("version").and_then(|version| version.as_u64()).unwrap_or(0);
+}
+
+/// Upgrades the data step by step until it reaches `CURRENT_DATA_VERSION`. Files without a version are version 0.
+pub fn migrate(data: &mut Value) -> Result<(), String> {
+    let version = get_data_version(data);
+    if version > CURRENT_DATA_VERSION {
+        return Err(format!("The data file has version {} but this term_do only understands up to version {}, please update term_do", version, CURRENT_DATA_VERSION));
+    }
+
+    let object = data.as_object_mut().ok_or("The data file doesn't contain a JSON object")?;
+    for step in version..CURRENT_DATA_VERSION {
+        MIGRATIONS[step as usize](object);
+        object.insert("version".to_string(), Value::from(step + 1));
+    }
+
+    return Ok(());
+}
+
+
+
+
+/// Version 1 gave permanent ids to groups and tasks and added due dates, priorities, notes and timestamps to tasks.
+/// Ids of older files are pre-order positions, unique inside their group, so tasks keep them and only the counters
+/// are added. Group ids were the amount of groups at creation time and could be repeated, so they are renumbered.
+fn migrate_v0_to_v1(data: &mut Map<String, Value>) {
+    let renumber_groups = !data.contains_key("next_group_id");
+    let mut groups_count = 0;
+
+    if let Some(groups) = data.get_mut("groups").and_then(|groups| groups.as_array_mut()) {
+        groups_count = groups.len();
+
+        for (i, group) in groups.iter_mut().enumerate() {
+            let group = match group.as_object_mut() {
+                Some(group) => group,
+                None => continue
+            };
+
+            if renumber_groups {
+                group.insert("id".to_string(), Value::from(i));
+            }
+
+            let mut next_task_id = group.get("next_task_id").and_then(|id| id.as_u64()).unwrap_or(0);
+            if let Some(tasks) = group.get_mut("tasks").and_then(|tasks| tasks.as_array_mut()) {
+                for task in tasks.iter_mut() {
+                    migrate_task_v0_to_v1(task, &mut next_task_id);
+                }
+            }
+
+            group.insert("next_task_id".to_string(), Value::from(next_task_id));
+            group.entry("sorted_by_priority").or_insert(Value::Bool(false));
+        }
+    }
+
+    if renumber_groups {
+        data.insert("next_group_id".to_string(), Value::from(groups_count));
+    }
+}
+
+fn migrate_task_v0_to_v1(task: &mut Value, next_task_id: &mut u64) {
+    let task = match task.as_object_mut() {
+        Some(task) => task,
+        None => return
+    };
+
+    if let Some(id) = task.get("id").and_then(|id| id.as_u64()) {
+        if id >= *next_task_id {
+            *next_task_id = id + 1;
+        }
+    }
+
+    task.entry("due_date").or_insert(Value::Null);
+    task.entry("due_time").or_insert(Value::Null);
+    task.entry("priority").or_insert(Value::from("None"));
+    task.entry("notes").or_insert(Value::from(""));
+    task.entry("created_at").or_insert(Value::Null);
+    task.entry("edited_at").or_insert(Value::Null);
+    task.entry("completed_at").or_insert(Value::Null);
+
+    if let Some(sub_tasks) = task.get_mut("tasks").and_then(|tasks| tasks.as_array_mut()) {
+        for sub_task in sub_tasks.iter_mut() {
+            migrate_task_v0_to_v1(sub_task, next_task_id);
+        }
+    }
+}
+
+/// Version 2 added an archive of completed tasks to every group.
+fn migrate_v1_to_v2(data: &mut Map<String, Value>) {
+    if let Some(groups) = data.get_mut("groups").and_then(|groups| groups.as_array_mut()) {
+        for group in groups.iter_mut().filter_map(|group| group.as_object_mut()) {
+            group.entry("archive").or_insert(Value::Array(Vec::new()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_manager::{DataManager, TaskItem};
+
+    // data.json as the first release wrote it, the groups share an id as they were created when there was one group
+    const BASELINE_DATA: &str = r#"{
+  "groups": [
+    {
+      "id": 1,
+      "name": "Work",
+      "tasks": [
+        {
+          "id": 0,
+          "done": false,
+          "name": "Write report",
+          "indentation": 0,
+          "parent": -1,
+          "tasks": [
+            { "id": 1, "done": true, "name": "Gather numbers", "indentation": 1, "parent": 0, "tasks": [], "folded": false },
+            {
+              "id": 2,
+              "done": false,
+              "name": "Draft",
+              "indentation": 1,
+              "parent": 0,
+              "tasks": [
+                { "id": 3, "done": true, "name": "Outline", "indentation": 2, "parent": 2, "tasks": [], "folded": false }
+              ],
+              "folded": true
+            }
+          ],
+          "folded": false
+        },
+        { "id": 4, "done": true, "name": "Send invoice", "indentation": 0, "parent": -1, "tasks": [], "folded": false }
+      ]
+    },
+    {
+      "id": 1,
+      "name": "Home",
+      "tasks": [
+        { "id": 0, "done": false, "name": "Groceries", "indentation": 0, "parent": -1, "tasks": [], "folded": false }
+      ]
+    }
+  ],
+  "selected_group": 1,
+  "selected_task": 0
+}"#;
+
+    // (id, parent, name, done, folded, indentation) of every task in pre-order
+    fn flatten(tasks: &Vec<TaskItem>, flattened: &mut Vec<(usize, isize, String, bool, bool, usize)>) {
+        for task in tasks {
+            flattened.push((task.id, task.parent, task.name.clone(), task.done, task.folded, task.indentation));
+            flatten(&task.tasks, flattened);
+        }
+    }
+
+    #[test]
+    fn baseline_data_keeps_every_task() {
+        let mut data : Value = serde_json::from_str(BASELINE_DATA).unwrap();
+        assert_eq!(get_data_version(&data), 0);
+
+        migrate(&mut data).unwrap();
+        assert_eq!(get_data_version(&data), CURRENT_DATA_VERSION);
+        assert_eq!(data["next_group_id"], 2);
+        assert_eq!(data["groups"][0]["next_task_id"], 5);
+        assert_eq!(data["groups"][1]["next_task_id"], 1);
+
+        let (data_manager, version) = DataManager::parse_data(data).unwrap();
+        assert_eq!(version, CURRENT_DATA_VERSION);
+
+        let groups = data_manager.get_group_items();
+        let ids : Vec<usize> = groups.iter().map(|group| group.get_id()).collect();
+        assert_eq!(ids, vec![0, 1]);
+        assert!(groups.iter().all(|group| group.get_archive().is_empty()));
+
+        let mut work = Vec::new();
+        flatten(groups[0].get_tasks(), &mut work);
+        assert_eq!(work, vec![
+            (0, -1, "Write report".to_string(), false, false, 0),
+            (1, 0, "Gather numbers".to_string(), true, false, 1),
+            (2, 0, "Draft".to_string(), false, true, 1),
+            (3, 2, "Outline".to_string(), true, false, 2),
+            (4, -1, "Send invoice".to_string(), true, false, 0)
+        ]);
+
+        let mut home = Vec::new();
+        flatten(groups[1].get_tasks(), &mut home);
+        assert_eq!(home, vec![(0, -1, "Groceries".to_string(), false, false, 0)]);
+    }
+
+    #[test]
+    fn each_step_sets_the_next_version() {
+        let mut data : Value = serde_json::from_str(BASELINE_DATA).unwrap();
+        let object = data.as_object_mut().unwrap();
+
+        migrate_v0_to_v1(object);
+        let task = &object["groups"][0]["tasks"][0];
+        assert_eq!(task["priority"], "None");
+        assert_eq!(task["tasks"][1]["tasks"][0]["notes"], "");
+        assert!(object["groups"][0].get("archive").is_none());
+
+        object.insert("version".to_string(), Value::from(1));
+        migrate_v1_to_v2(object);
+        assert_eq!(object["groups"][1]["archive"], Value::Array(Vec::new()));
+
+        migrate(&mut data).unwrap();
+        assert_eq!(get_data_version(&data), CURRENT_DATA_VERSION);
+    }
+
+    #[test]
+    fn newer_data_is_refused() {
+        let mut data = serde_json::json!({ "groups": [], "version": CURRENT_DATA_VERSION + 1 });
+        assert!(migrate(&mut data).is_err());
+    }
+}
+
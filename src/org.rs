@@ -0,0 +1,160 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::data_manager::{DataManager, GroupItem, TaskItem};
+use crate::import::{add_imported_tasks, ImportedTask};
+
+/// An Emacs Org file with a top level heading for every group and a TODO or DONE heading one level deeper for every
+/// subtask level. Folded tasks have the `:VISIBILITY: folded` property and unfolded ones with subtasks
+/// `:VISIBILITY: children`, so Org opens them the same way. Due dates are deadlines and notes the text under the heading.
+pub fn export_groups(groups: &Vec<&GroupItem>) -> String {
+    let mut org = String::new();
+    for group in groups {
+        org.push_str(&format!("* {}\n", group.name));
+        export_tasks_recursive(group.get_tasks(), 2, &mut org);
+    }
+
+    return org;
+}
+
+fn export_tasks_recursive(tasks: &Vec<TaskItem>, level: usize, org: &mut String) {
+    let indentation = " ".repeat(level + 1);
+
+    for task in tasks {
+        org.push_str(&format!("{} {} {}\n", "*".repeat(level), if task.done { "DONE" } else { "TODO" }, task.name.replace(['\n', '\r'], " ")));
+
+        let mut planning : Vec<String> = Vec::new();
+        if let (true, Some(completed_at)) = (task.done, task.completed_at) {
+            planning.push(format!("CLOSED: [{}]", completed_at.format("%Y-%m-%d %a %H:%M")));
+        }
+        if let Some(due_date) = task.due_date {
+            match task.due_time {
+                Some(due_time) => planning.push(format!("DEADLINE: <{}>", due_date.and_time(due_time).format("%Y-%m-%d %a %H:%M"))),
+                None => planning.push(format!("DEADLINE: <{}>", due_date.format("%Y-%m-%d %a")))
+            }
+        }
+        if !planning.is_empty() {
+            org.push_str(&format!("{}{}\n", indentation, planning.join(" ")));
+        }
+
+        if !task.tasks.is_empty() {
+            org.push_str(&format!("{}:PROPERTIES:\n{}:VISIBILITY: {}\n{}:END:\n", indentation, indentation, if task.folded { "folded" } else { "children" }, indentation));
+        }
+
+        for line in task.notes.lines() {
+            org.push_str(&format!("{}{}\n", indentation, line));
+        }
+
+        export_tasks_recursive(&task.tasks, level + 1, org);
+    }
+}
+
+/// Adds the headings of an Org file as tasks. Top level headings are groups, found by name or created, and the
+/// headings under them their tasks, nested as the headings are. Top level headings with a TODO or DONE keyword are
+/// tasks too, they go to `default_group` like headings before the first group. Returns how many tasks were added.
+pub fn import(data_manager: &mut DataManager, content: &str, default_group: &str) -> Result<usize, String> {
+    let mut tasks : Vec<ImportedTask> = Vec::new();
+    let mut group = default_group.to_string();
+    // Level and key of the headings that can still get subtasks, from the outermost one
+    let mut parents : Vec<(usize, String)> = Vec::new();
+    let mut in_properties = false;
+    // Text under a group heading isn't part of any task
+    let mut in_task = false;
+
+    for line in content.lines() {
+        let level = line.chars().take_while(|c| *c == '*').count();
+        let is_heading = level > 0 && line[level..].starts_with(' ');
+
+        if !is_heading {
+            let task = match tasks.last_mut() {
+                Some(task) if in_task => task,
+                _ => continue
+            };
+            let text = line.trim();
+
+            if in_properties {
+                if text.eq_ignore_ascii_case(":END:") {
+                    in_properties = false;
+                } else if let Some(visibility) = text.strip_prefix(":VISIBILITY:") {
+                    task.fields.folded = visibility.trim().eq("folded");
+                }
+            } else if text.eq_ignore_ascii_case(":PROPERTIES:") {
+                in_properties = true;
+            } else if text.starts_with("CLOSED:") || text.starts_with("DEADLINE:") || text.starts_with("SCHEDULED:") {
+                read_planning(text, &mut task.fields);
+            } else if !text.is_empty() || !task.fields.notes.is_empty() {
+                task.fields.notes.push_str(text);
+                task.fields.notes.push('\n');
+            }
+            continue;
+        }
+
+        in_properties = false;
+        let heading = line[level..].trim();
+        let (done, name) = match heading.split_once(' ').unwrap_or((heading, "")) {
+            ("TODO", name) => (Some(false), name.trim()),
+            ("DONE", name) => (Some(true), name.trim()),
+            _ => (None, heading)
+        };
+
+        in_task = level > 1 || done.is_some();
+        if !in_task {
+            group = name.to_string();
+            parents.clear();
+            continue;
+        }
+        if level == 1 {
+            group = default_group.to_string();
+        }
+
+        while let Some(parent) = parents.last() {
+            if parent.0 < level {
+                break;
+            }
+            parents.pop();
+        }
+
+        let key = tasks.len().to_string();
+        let mut task = ImportedTask { group: group.clone(), key: Some(key.clone()), parent: parents.last().map(|parent| parent.1.clone()), fields: TaskItem::new(name.to_string(), 0, -1) };
+        if done == Some(true) {
+            task.fields.set_done(true);
+        }
+
+        parents.push((level, key));
+        tasks.push(task);
+    }
+
+    for task in &mut tasks {
+        task.fields.notes = task.fields.notes.trim_end().to_string();
+    }
+
+    return Ok(add_imported_tasks(data_manager, tasks));
+}
+
+/// The completion and due dates of a `CLOSED: [...] DEADLINE: <...>` line.
+fn read_planning(line: &str, task: &mut TaskItem) {
+    if let Some(closed) = line.split_once("CLOSED:").and_then(|(_, rest)| parse_timestamp(rest)) {
+        if task.done {
+            task.completed_at = Some(closed);
+        }
+    }
+
+    if let Some((_, rest)) = line.split_once("DEADLINE:") {
+        let has_time = rest.split(['>', ']']).next().unwrap_or("").split_whitespace().any(|word| NaiveTime::parse_from_str(word, "%H:%M").is_ok());
+        if let Some(deadline) = parse_timestamp(rest) {
+            task.due_date = Some(deadline.date());
+            task.due_time = if has_time { Some(deadline.time()) } else { None };
+        }
+    }
+}
+
+/// Parses the first `<2026-10-20 Tue 15:30>` or `[2026-10-20 Tue]` timestamp of the text.
+fn parse_timestamp(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim_start().strip_prefix(['<', '['])?;
+    let text = text.split(['>', ']']).next()?;
+
+    let mut words = text.split_whitespace();
+    let date = NaiveDate::parse_from_str(words.next()?, "%Y-%m-%d").ok()?;
+    let time = words.find_map(|word| NaiveTime::parse_from_str(word, "%H:%M").ok()).unwrap_or(NaiveTime::MIN);
+
+    return Some(date.and_time(time));
+}
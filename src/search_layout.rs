@@ -0,0 +1,85 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::backend::Backend;
+use tui::Frame;
+use tui::layout::Rect;
+use crate::{App, DataManager, FocusedLayout, LayoutCommon, LayoutCommonTrait};
+
+use unicode_width::UnicodeWidthStr;
+use crate::enums::InputMode;
+
+pub struct SearchLayout {
+    pub(crate) layout_common: LayoutCommon,
+    pub(crate) last_match: Option<bool>,
+    searching_from_task: bool
+}
+
+impl SearchLayout {
+    pub fn new() -> SearchLayout {
+        SearchLayout {
+            layout_common: LayoutCommon::new(),
+            last_match: None,
+            searching_from_task: false
+        }
+    }
+
+    pub fn open(&mut self, data_manager: &mut DataManager, focused_layout: FocusedLayout) {
+        self.layout_common.input_mode = InputMode::Add;
+        self.layout_common.input = String::new();
+        self.layout_common.cursor_pos = self.layout_common.input.width();
+        self.searching_from_task = focused_layout == FocusedLayout::TasksLayout;
+        self.last_match = None;
+        data_manager.search_query.clear();
+
+        LayoutCommon::recalculate_input_string_starting_point(&mut self.layout_common);
+    }
+
+    /// Jumps to the next or previous match of the last search, used by 'n' and 'N'.
+    pub fn jump(&mut self, data_manager: &mut DataManager, focused_layout: FocusedLayout, forward: bool) {
+        self.last_match = data_manager.select_search_match(focused_layout == FocusedLayout::TasksLayout, forward, false);
+    }
+}
+
+
+
+
+
+
+impl LayoutCommonTrait for SearchLayout {
+
+    fn handle_input(&mut self, data_manager: &mut DataManager, key_code: KeyEvent) {
+        if !self.layout_common.is_in_edit_mode() {
+            return;
+        }
+
+        if data_manager.config.get_key("apply_search") == key_code.code {
+            self.layout_common.input_mode = InputMode::Navigate;
+            return;
+        }
+
+        if key_code.code == KeyCode::Esc {
+            data_manager.search_query.clear();
+            self.last_match = None;
+        }
+
+        <SearchLayout as LayoutCommonTrait>::poll_common_keys_input_mode(&key_code, &mut self.layout_common);
+
+        if self.layout_common.is_in_edit_mode() && !data_manager.search_query.eq(&self.layout_common.input) {
+            data_manager.search_query = self.layout_common.input.clone();
+            self.last_match = data_manager.select_search_match(self.searching_from_task, true, true);
+            if let Some(is_task) = self.last_match {
+                self.searching_from_task = is_task;
+            }
+        }
+    }
+
+    fn ui<B: Backend>(_f: &mut Frame<B>, _app: &mut App, _chunk: &Vec<Rect>, _frame_size: &Rect) {  }
+
+    fn create_and_render_base_block<B: Backend>(_f: &mut Frame<B>, _app: &mut App, _chunk: &Vec<Rect>) {  }
+
+    fn create_and_render_item_list<B: Backend>(_f: &mut Frame<B>, _app: &mut App, _chunk: &Vec<Rect>, _frame_size: &Rect) {  }
+
+    fn create_and_render_edit_mode<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>) {
+        let title = if app.data_manager.search_query.is_empty() || app.search_layout.last_match.is_some() { "Search" } else { "Search (no matches)" };
+        <SearchLayout as LayoutCommonTrait>::render_common_input_mode(f, &mut app.search_layout.layout_common, title, chunk);
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::time::SystemTime;
+
+use chrono::Local;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{Map, Value};
+
+use crate::config_manager::ConfigManager;
+
+/// Where the data is persisted. Backends return the data in the same layout as data.json, so migrations and
+/// everything above them don't depend on how it is stored.
+pub trait StorageBackend {
+    fn get_file_path(&self) -> String;
+
+    /// Reads the data stored in `path`, which is the data file or one of its backups. Ok(None) if nothing is stored yet.
+    fn read(&mut self, path: &str) -> Result<Option<Value>, String>;
+
+    /// Writes the fields of data.json besides the groups, and the groups in the order of `group_ids`. Only the groups
+    /// that changed since the last write are in `changed_groups`, the others are the ones read from the data file or
+    /// written before.
+    fn write(&mut self, fields: &Map<String, Value>, group_ids: &Vec<u64>, changed_groups: &HashMap<u64, Value>) -> Result<(), String>;
+}
+
+pub fn create_storage(config: &ConfigManager) -> Box<dyn StorageBackend> {
+    let data_path = config.path.get("data_path").unwrap();
+    let backup_count = config.get_backup_count();
+
+    return match config.path.get("storage").unwrap().trim().to_lowercase().as_str() {
+        "sqlite" => Box::new(SqliteStorage::new(format!("{}/data.db", data_path), backup_count)),
+        _ => Box::new(JsonStorage::new(format!("{}/data.json", data_path), backup_count))
+    };
+}
+
+pub fn get_backups(file: &str, backup_count: usize) -> Vec<(usize, SystemTime, u64)> {
+    let mut backups : Vec<(usize, SystemTime, u64)> = Vec::new();
+    for number in 1..=backup_count {
+        if let Ok(metadata) = fs::metadata(format!("{}.{}", file, number)) {
+            backups.push((number, metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), metadata.len()));
+        }
+    }
+
+    return backups;
+}
+
+pub fn rotate_backups(file: &str, backup_count: usize) -> Result<(), String> {
+    if backup_count == 0 || fs::metadata(file).is_err() {
+        return Ok(());
+    }
+
+    for number in (1..backup_count).rev() {
+        let backup_file = format!("{}.{}", file, number);
+        if fs::metadata(&backup_file).is_ok() {
+            fs::rename(&backup_file, format!("{}.{}", file, number + 1)).map_err(|error| format!("Couldn't rotate backups of {}: {}", file, error))?;
+        }
+    }
+
+    fs::copy(file, format!("{}.1", file)).map_err(|error| format!("Couldn't back up {}: {}", file, error))?;
+    return Ok(());
+}
+
+/// Writes to a temporary file that is renamed over `file`, so a crash mid-write never leaves a half written file.
+/// The previous content is kept as a backup.
+pub fn write_file_atomically(file: &str, content: &[u8], backup_count: usize) -> Result<(), String> {
+    let temp_file = format!("{}.tmp", file);
+
+    let mut temp = File::create(&temp_file).map_err(|error| format!("Couldn't create {}: {}", temp_file, error))?;
+    temp.write_all(content).map_err(|error| format!("Couldn't write {}: {}", temp_file, error))?;
+    temp.sync_all().map_err(|error| format!("Couldn't flush {}: {}", temp_file, error))?;
+
+    rotate_backups(file, backup_count)?;
+    fs::rename(&temp_file, file).map_err(|error| format!("Couldn't replace {}: {}", file, error))?;
+    return Ok(());
+}
+
+pub fn append_to_file(file: &str, content: &[u8]) -> Result<(), String> {
+    let mut opened = OpenOptions::new().create(true).append(true).open(file).map_err(|error| format!("Couldn't open {}: {}", file, error))?;
+    opened.write_all(content).map_err(|error| format!("Couldn't write {}: {}", file, error))?;
+    return Ok(());
+}
+
+pub fn restore_backup(file: &str, number: usize, backup_count: usize) -> Result<(), String> {
+    let backup_file = format!("{}.{}", file, number);
+    let content = fs::read(&backup_file).map_err(|error| format!("Couldn't read {}: {}", backup_file, error))?;
+    return write_file_atomically(file, &content, backup_count);
+}
+
+pub fn move_file_aside(file: &str) -> Result<String, String> {
+    let broken_file = format!("{}.broken-{}", file, Local::now().format("%Y%m%d-%H%M%S"));
+    fs::rename(file, &broken_file).map_err(|error| format!("Couldn't move {} to {}: {}", file, broken_file, error))?;
+
+    return Ok(broken_file);
+}
+
+
+
+
+
+/// data.json rewritten as a whole on every save. Backups are taken once per session, before the first write, so they
+/// keep the data of previous sessions instead of the last few actions. The groups are kept as written in the file,
+/// so a save only serializes the groups that changed.
+pub struct JsonStorage {
+    file_path: String,
+    backup_count: usize,
+    written_groups: HashMap<u64, String>,
+    backed_up: bool
+}
+
+impl JsonStorage {
+    pub fn new(file_path: String, backup_count: usize) -> JsonStorage {
+        JsonStorage {
+            file_path,
+            backup_count,
+            written_groups: HashMap::new(),
+            backed_up: false
+        }
+    }
+
+    fn to_group_json(group: &Value) -> Result<String, String> {
+        return serde_json::to_string_pretty(group).map(|json| json.replace('\n', "\n    ")).map_err(|error| error.to_string());
+    }
+
+    /// The same text `serde_json::to_string_pretty` makes of the whole data, the fields sorted by name.
+    fn to_data_json(fields: &Map<String, Value>, groups: &Vec<&String>) -> Result<String, String> {
+        let mut entries : Vec<(String, String)> = Vec::new();
+        for (key, value) in fields {
+            let json = serde_json::to_string_pretty(value).map_err(|error| error.to_string())?;
+            entries.push((key.clone(), json.replace('\n', "\n  ")));
+        }
+
+        let groups_json = if groups.is_empty() {
+            "[]".to_string()
+        } else {
+            format!("[\n    {}\n  ]", groups.iter().map(|group| group.as_str()).collect::<Vec<&str>>().join(",\n    "))
+        };
+        entries.push(("groups".to_string(), groups_json));
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let lines : Vec<String> = entries.iter().map(|(key, json)| format!("  {}: {}", Value::String(key.clone()), json)).collect();
+        return Ok(format!("{{\n{}\n}}", lines.join(",\n")));
+    }
+}
+
+impl StorageBackend for JsonStorage {
+    fn get_file_path(&self) -> String {
+        return self.file_path.clone();
+    }
+
+    fn read(&mut self, path: &str) -> Result<Option<Value>, String> {
+        let content = match fs::read_to_string(path) {
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(format!("Couldn't read {}: {}", path, error)),
+            Ok(content) => content
+        };
+
+        let data : Value = serde_json::from_str(&content).map_err(|error| error.to_string())?;
+
+        if path.eq(&self.file_path) {
+            self.written_groups.clear();
+            for group in data.get("groups").and_then(|groups| groups.as_array()).unwrap_or(&Vec::new()) {
+                if let Some(id) = group.get("id").and_then(|id| id.as_u64()) {
+                    self.written_groups.insert(id, JsonStorage::to_group_json(group)?);
+                }
+            }
+        }
+
+        return Ok(Some(data));
+    }
+
+    fn write(&mut self, fields: &Map<String, Value>, group_ids: &Vec<u64>, changed_groups: &HashMap<u64, Value>) -> Result<(), String> {
+        let mut groups : HashMap<u64, String> = HashMap::new();
+        for (id, group) in changed_groups {
+            groups.insert(*id, JsonStorage::to_group_json(group)?);
+        }
+
+        let mut ordered_groups : Vec<&String> = Vec::new();
+        for id in group_ids {
+            ordered_groups.push(groups.get(id).or(self.written_groups.get(id)).ok_or(format!("Group {} was never written", id))?);
+        }
+        let content = JsonStorage::to_data_json(fields, &ordered_groups)?;
+
+        if !self.backed_up {
+            rotate_backups(&self.file_path, self.backup_count)?;
+            self.backed_up = true;
+        }
+        write_file_atomically(&self.file_path, content.as_bytes(), 0)?;
+
+        let kept_ids : HashSet<&u64> = group_ids.iter().collect();
+        self.written_groups.retain(|id, _| kept_ids.contains(id));
+        self.written_groups.extend(groups);
+        return Ok(());
+    }
+}
+
+
+
+
+
+type GroupRow = (u64, usize, String);
+
+type StoredData = (Map<String, Value>, Vec<GroupRow>);
+
+/// Embedded SQLite database, each group is a row holding its tasks, so a change only rewrites the groups it touched
+/// instead of the whole data. Backups are taken once per session, before the first write. `written_groups` holds
+/// the position of every group in the database.
+pub struct SqliteStorage {
+    file_path: String,
+    backup_count: usize,
+    connection: Option<Connection>,
+    written_groups: HashMap<u64, usize>,
+    backed_up: bool
+}
+
+impl SqliteStorage {
+    pub fn new(file_path: String, backup_count: usize) -> SqliteStorage {
+        SqliteStorage {
+            file_path,
+            backup_count,
+            connection: None,
+            written_groups: HashMap::new(),
+            backed_up: false
+        }
+    }
+
+    fn create_tables(connection: &Connection) -> rusqlite::Result<()> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS groups (id INTEGER PRIMARY KEY, position INTEGER NOT NULL, data TEXT NOT NULL);"
+        )
+    }
+
+    fn read_rows(connection: &Connection) -> rusqlite::Result<Option<StoredData>> {
+        let has_tables : Option<String> = connection.query_row("SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'meta'", [], |row| row.get(0)).optional()?;
+        if has_tables.is_none() {
+            return Ok(None);
+        }
+
+        let mut meta : Map<String, Value> = Map::new();
+        let mut statement = connection.prepare("SELECT key, value FROM meta")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (key, value) = row?;
+            meta.insert(key, serde_json::from_str(&value).unwrap_or(Value::Null));
+        }
+
+        if meta.is_empty() {
+            return Ok(None);
+        }
+
+        let mut groups : Vec<GroupRow> = Vec::new();
+        let mut statement = connection.prepare("SELECT id, position, data FROM groups ORDER BY position")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as usize, row.get::<_, String>(2)?)))?;
+        for row in rows {
+            groups.push(row?);
+        }
+
+        return Ok(Some((meta, groups)));
+    }
+
+    fn get_connection(&mut self) -> Result<&mut Connection, String> {
+        if self.connection.is_none() {
+            let connection = Connection::open(&self.file_path).map_err(|error| format!("Couldn't open {}: {}", self.file_path, error))?;
+            SqliteStorage::create_tables(&connection).map_err(|error| error.to_string())?;
+            self.connection = Some(connection);
+        }
+
+        return Ok(self.connection.as_mut().unwrap());
+    }
+
+    fn write_rows(&mut self, fields: &Map<String, Value>, group_ids: &Vec<u64>, changed_groups: &HashMap<u64, Value>, written_groups: &HashMap<u64, usize>) -> Result<(), String> {
+        let connection = self.get_connection()?;
+        let transaction = connection.transaction().map_err(|error| error.to_string())?;
+
+        for (key, value) in fields {
+            transaction.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", params![key, value.to_string()]).map_err(|error| error.to_string())?;
+        }
+
+        for (position, id) in group_ids.iter().enumerate() {
+            if let Some(group) = changed_groups.get(id) {
+                transaction.execute("INSERT OR REPLACE INTO groups (id, position, data) VALUES (?1, ?2, ?3)", params![*id as i64, position as i64, group.to_string()]).map_err(|error| error.to_string())?;
+            } else {
+                match written_groups.get(id) {
+                    Some(written_position) if *written_position == position => {},
+                    Some(_) => {
+                        transaction.execute("UPDATE groups SET position = ?1 WHERE id = ?2", params![position as i64, *id as i64]).map_err(|error| error.to_string())?;
+                    },
+                    None => return Err(format!("Group {} was never written", id))
+                }
+            }
+        }
+
+        let kept_ids : HashSet<&u64> = group_ids.iter().collect();
+        for id in written_groups.keys() {
+            if !kept_ids.contains(id) {
+                transaction.execute("DELETE FROM groups WHERE id = ?1", params![*id as i64]).map_err(|error| error.to_string())?;
+            }
+        }
+
+        transaction.commit().map_err(|error| error.to_string())?;
+        return Ok(());
+    }
+}
+
+impl StorageBackend for SqliteStorage {
+    fn get_file_path(&self) -> String {
+        return self.file_path.clone();
+    }
+
+    fn read(&mut self, path: &str) -> Result<Option<Value>, String> {
+        if fs::metadata(path).is_err() {
+            return Ok(None);
+        }
+
+        let connection = Connection::open(path).map_err(|error| format!("Couldn't open {}: {}", path, error))?;
+        let rows = SqliteStorage::read_rows(&connection).map_err(|error| error.to_string())?;
+        let (mut data, groups) = match rows {
+            Some(rows) => rows,
+            None => return Ok(None)
+        };
+
+        let mut groups_json : Vec<Value> = Vec::new();
+        for (_, _, group) in &groups {
+            groups_json.push(serde_json::from_str(group).map_err(|error| error.to_string())?);
+        }
+        data.insert("groups".to_string(), Value::Array(groups_json));
+
+        if path.eq(&self.file_path) {
+            self.written_groups = groups.into_iter().map(|(id, position, _)| (id, position)).collect();
+        }
+
+        return Ok(Some(Value::Object(data)));
+    }
+
+    fn write(&mut self, fields: &Map<String, Value>, group_ids: &Vec<u64>, changed_groups: &HashMap<u64, Value>) -> Result<(), String> {
+        if !self.backed_up {
+            rotate_backups(&self.file_path, self.backup_count)?;
+            self.backed_up = true;
+        }
+
+        // If the write fails nothing was committed, so what is in the database is still `written_groups`
+        let written_groups = std::mem::take(&mut self.written_groups);
+        let result = self.write_rows(fields, group_ids, changed_groups, &written_groups);
+        self.written_groups = if result.is_ok() { group_ids.iter().enumerate().map(|(position, id)| (*id, position)).collect() } else { written_groups };
+
+        return result;
+    }
+}
@@ -1,21 +1,27 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Add;
-use crossterm::event::KeyEvent;
+use chrono::{Local, NaiveDateTime};
+use crossterm::event::{KeyCode, KeyEvent};
 use tui::backend::Backend;
 use tui::Frame;
 use tui::layout::Rect;
-use tui::style::{Modifier, Style};
-use tui::widgets::{Block, Borders, BorderType, List, ListItem};
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, Borders, BorderType, Clear, List, ListItem};
 use crate::{App, centered_rect, DataManager, FocusedLayout, LayoutCommon, LayoutCommonTrait};
 
 use crate::data_manager::{GroupItem, TaskItem};
 
 use unicode_width::UnicodeWidthStr;
-use crate::enums::InputMode;
+use crate::enums::{InputMode, Priority};
 
 pub struct TaskLayout {
     pub(crate) layout_common: LayoutCommon,
     is_adding_subtask: bool,
+    is_due_date_invalid: bool,
+    notes_task_name: String,
+    picked_group: usize,
+    is_copying_to_group: bool,
+    delete_question: String,
     width_of_chunk: usize
 }
 
@@ -29,17 +35,28 @@ impl TaskLayout {
         TaskLayout {
             layout_common: LayoutCommon::new(),
             is_adding_subtask: false,
+            is_due_date_invalid: false,
+            notes_task_name: String::new(),
+            picked_group: 0,
+            is_copying_to_group: false,
+            delete_question: String::new(),
             width_of_chunk: 0
         }
     }
 
-    pub  fn recursive_sub_tasks<'a>(&self, data_manager: &'a DataManager, tasks: &'a Vec<TaskItem>, frame_size: &Rect) -> Vec<ListItem<'a>> {
+    pub  fn recursive_sub_tasks<'a>(&self, data_manager: &'a DataManager, tasks: &'a Vec<TaskItem>, positions: &HashMap<usize, usize>, filtered_out: &HashSet<usize>, frame_size: &Rect) -> Vec<ListItem<'a>> {
         let mut item_list : Vec<ListItem> = Vec::new();
         let height = ListItem::new("Hello").style(Style::default()).height();
         let max_lines : usize = (frame_size.height as usize / (2 * height)) as usize;
         let showing_start_item = if data_manager.selected_task > max_lines { data_manager.selected_task - max_lines } else { 0 };
+        let now = Local::now().naive_local();
+        let tasks = data_manager.get_group_read_only(data_manager.selected_group).get_ordered_tasks(tasks);
 
         for i in showing_start_item..tasks.len() {
+            if filtered_out.contains(&tasks[i].id) {
+                continue;
+            }
+
             let line = tasks[i].name.as_str();
             let mut indented_line = String::new();
 
@@ -50,11 +67,15 @@ impl TaskLayout {
                 iconed_line = format!("{}{}", data_manager.config.task.get("icon_completed").unwrap(), "  ").to_string();
                 default_style = default_style.fg(data_manager.config.get_color("task", "completed_color"));
             } else {
-                default_style = default_style.fg(data_manager.config.get_color("task", "non_selected_color"));
+                default_style = default_style.fg(TaskLayout::pending_task_color(data_manager, &tasks[i], now));
                 iconed_line = format!("{}{}", data_manager.config.task.get("icon_uncompleted").unwrap(), "  ").to_string();
             }
 
+            iconed_line.push_str(TaskLayout::priority_string(data_manager, &tasks[i]).as_str());
             iconed_line.push_str(line);
+            if !tasks[i].notes.is_empty() {
+                iconed_line.push_str(format!(" {}", data_manager.config.task.get("icon_notes").unwrap()).as_str());
+            }
 
             if tasks[i].indentation > 1 {
 
@@ -66,7 +87,10 @@ impl TaskLayout {
                 while top_parent != -1 {
                     let top_parent_task = GroupItem::get_task_recursive_read_only(top_parent as usize, gi.get_tasks()).unwrap();
                     for t in top_parent_task.0.get_tasks() {
-                        if t.id > tasks[i].id {
+                        if filtered_out.contains(&t.id) {
+                            continue;
+                        }
+                        if positions[&t.id] > positions[&tasks[i].id] {
                             amount_of_fucking_vertical_sticks.insert((*top_parent_task.0).indentation, ParentInfo { needs_vertical_bar: true, is_folded: top_parent_task.0.folded });
                             continue;
                         }
@@ -99,6 +123,7 @@ impl TaskLayout {
 
                     let sub_tasks_string = TaskLayout::sub_tasks_string(data_manager, tasks[i].get_tasks());
                     indented_line.push_str(sub_tasks_string.as_str());
+                    indented_line.push_str(TaskLayout::due_date_string(&tasks[i]).as_str());
 
                     indented_line = TaskLayout::break_line_if_needed(self, indented_line, &indentation_string);
                 }
@@ -116,11 +141,16 @@ impl TaskLayout {
                     indented_line = std::iter::repeat(l).take(tasks[i].indentation).collect::<String>().add(iconed_line.as_str());
                     let sub_tasks_string = TaskLayout::sub_tasks_string(data_manager, tasks[i].get_tasks());
                     indented_line.push_str(sub_tasks_string.as_str());
+                    indented_line.push_str(TaskLayout::due_date_string(&tasks[i]).as_str());
                     indented_line = TaskLayout::break_line_if_needed(self, indented_line, "     ");
                 }
             }
 
-            if tasks[i].id == data_manager.selected_task {
+            if data_manager.matches_search(tasks[i].name.as_str()) {
+                default_style = default_style.add_modifier(Modifier::REVERSED);
+            }
+
+            if positions[&tasks[i].id] == data_manager.selected_task {
                 default_style = default_style.fg(data_manager.config.get_color("task", "selected_color"));
                 item_list.push(ListItem::new(indented_line).style(default_style));
             } else {
@@ -128,7 +158,7 @@ impl TaskLayout {
             }
 
             if !tasks[i].get_tasks().is_empty() {
-                let mut new_task_items = TaskLayout::recursive_sub_tasks(self, data_manager, tasks[i].get_tasks(), frame_size);
+                let mut new_task_items = TaskLayout::recursive_sub_tasks(self, data_manager, tasks[i].get_tasks(), positions, filtered_out, frame_size);
                 item_list.append(&mut new_task_items);
             }
         }
@@ -162,6 +192,92 @@ impl TaskLayout {
         return broke_line;
     }
 
+    fn delete_selected_task(data_manager: &mut DataManager) {
+        data_manager.apply();
+
+        let selected_task = data_manager.get_selected_task_id().unwrap();
+        let selected_group = data_manager.selected_group;
+        let parent_of_deleted : isize;
+        let task_ro : (TaskItem, isize);
+
+
+        let gi_ro = data_manager.get_group_read_only(selected_group);
+        let tasks = gi_ro.get_tasks();
+        let task = GroupItem::get_task_recursive_read_only(selected_task, tasks).unwrap();
+        task_ro = (task.0.clone(), task.1);
+        parent_of_deleted = task.0.parent;
+
+
+        let gi = data_manager.get_group(selected_group);
+        gi.remove_task((&task_ro.0, task_ro.1));
+
+        if parent_of_deleted != -1 {
+            gi.update_parents_to_check_if_all_completed(parent_of_deleted as usize);
+        }
+
+        let tasks_left = gi.get_tasks_and_subtasks_count().0;
+        if data_manager.selected_task >= tasks_left {
+            data_manager.selected_task = if tasks_left > 0 { tasks_left - 1 } else { 0 };
+        }
+
+        if data_manager.folded_state.contains_key(&data_manager.selected_task) {
+            data_manager.folded_state.remove(&data_manager.selected_task);
+        }
+
+        data_manager.load_folding(data_manager.selected_group);
+        let _ = data_manager.save_state();
+    }
+
+    fn render_group_picker<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>) {
+        let title = if app.task_layout.is_copying_to_group { "Copy task to group" } else { "Move task to group" };
+        let area = centered_rect(40, 40, chunk[1]);
+
+        let mut items_list : Vec<ListItem> = Vec::new();
+        for (i, group) in app.data_manager.get_group_items().iter().enumerate() {
+            let line = format!("{}  {}", app.data_manager.config.group.get("icon").unwrap(), group.name);
+            let color = if i == app.task_layout.picked_group { app.data_manager.config.get_color("group", "selected_color") } else { app.data_manager.config.get_color("group", "non_selected_color") };
+            items_list.push(ListItem::new(line).style(Style::default().fg(color)));
+        }
+
+        let items = List::new(items_list)
+            .block(Block::default().title(title).borders(Borders::ALL));
+
+        f.render_widget(Clear, area);
+        f.render_widget(items, area);
+    }
+
+    fn due_date_string(task: &TaskItem) -> String {
+        if task.due_date.is_none() {
+            return String::new();
+        }
+
+        return format!(" [{}]", task.get_due_string());
+    }
+
+    fn pending_task_color(data_manager: &DataManager, task: &TaskItem, now: NaiveDateTime) -> Color {
+        if task.is_overdue(now) {
+            return data_manager.config.get_color("task", "overdue_color");
+        }
+
+        if task.is_due_today(now) {
+            return data_manager.config.get_color("task", "due_today_color");
+        }
+
+        if task.priority != Priority::None {
+            return data_manager.config.get_color("task", format!("priority_{}_color", task.priority.config_name()).as_str());
+        }
+
+        return data_manager.config.get_color("task", "non_selected_color");
+    }
+
+    fn priority_string(data_manager: &DataManager, task: &TaskItem) -> String {
+        if task.priority == Priority::None {
+            return String::new();
+        }
+
+        return format!("{} ", data_manager.config.task.get(format!("priority_{}_icon", task.priority.config_name())).unwrap());
+    }
+
     fn sub_tasks_string(data_manager: &DataManager, tasks: &Vec<TaskItem>) -> String {
         let selected_group = data_manager.selected_group;
         let gi = data_manager.get_group_read_only(selected_group);
@@ -197,6 +313,7 @@ impl LayoutCommonTrait for TaskLayout {
                 } else if data_manager.config.get_key("add_subtask") == key_code.code {
                     if data_manager.get_group_items().is_empty() { return; }
                     if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
 
                     self.layout_common.input_mode = InputMode::Add;
                     self.is_adding_subtask = true;
@@ -207,11 +324,12 @@ impl LayoutCommonTrait for TaskLayout {
                 } else if data_manager.config.get_key("edit_task_or_subtask") == key_code.code {
                     if data_manager.get_group_items().is_empty() { return; }
                     if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
 
                     self.layout_common.input_mode = InputMode::Edit;
                     self.is_adding_subtask = false;
 
-                    let selected_task = data_manager.selected_task;
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
                     let selected_group = data_manager.selected_group;
                     let gi = data_manager.get_group(selected_group);
                     let task_name = GroupItem::get_task_recursive_read_only(selected_task, gi.get_tasks()).unwrap().0.name.clone();
@@ -223,103 +341,214 @@ impl LayoutCommonTrait for TaskLayout {
                 } else if data_manager.config.get_key("delete_task_or_subtask") == key_code.code {
                     if data_manager.get_group_items().is_empty() { return; }
                     if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
+
+                    if !data_manager.config.get_bool("task", "ask_before_delete") {
+                        TaskLayout::delete_selected_task(data_manager);
+                        return;
+                    }
+
+                    let gi = data_manager.get_group_read_only(data_manager.selected_group);
+                    let task = GroupItem::get_task_recursive_read_only(data_manager.get_selected_task_id().unwrap(), gi.get_tasks()).unwrap().0;
+                    let sub_tasks = match gi.get_tasks_and_subtasks_count_specific(task.get_tasks()).0 {
+                        0 => String::new(),
+                        1 => " with 1 subtask".to_string(),
+                        count => format!(" with {} subtasks", count)
+                    };
+                    self.delete_question = format!("Delete task '{}'{}? Press '{}' to delete, any other key to cancel", task.name, sub_tasks, data_manager.config.get_key_name("accept_confirmation"));
+                    self.layout_common.input_mode = InputMode::Confirm;
+                } else if data_manager.config.get_key("complete_or_uncomplete_task") == key_code.code {
+                    if data_manager.get_group_items().is_empty() { return; }
+                    if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
 
                     data_manager.apply();
 
-                    let selected_task = data_manager.selected_task;
-                    let selected_group = data_manager.selected_group;
-                    let parent_of_deleted : isize;
-                    let task_ro : (TaskItem, isize);
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
+                    let gi = data_manager.get_group(data_manager.selected_group);
+                    gi.set_task_and_subtasks_done_or_undone(selected_task, None);
 
+                    let _ = data_manager.save_state();
+                } else if data_manager.config.get_key("archive_task") == key_code.code {
+                    if data_manager.get_group_items().is_empty() { return; }
+                    if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
 
-                    let gi_ro = data_manager.get_group_read_only(selected_group);
-                    let tasks = gi_ro.get_tasks();
-                    let task = GroupItem::get_task_recursive_read_only(selected_task, tasks).unwrap();
-                    task_ro = (task.0.clone(), task.1);
-                    parent_of_deleted = task.0.parent;
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
+                    let selected_group = data_manager.selected_group;
+                    if !GroupItem::get_task_recursive_read_only(selected_task, data_manager.get_group_read_only(selected_group).get_tasks()).unwrap().0.done {
+                        return;
+                    }
 
+                    data_manager.apply();
 
                     let gi = data_manager.get_group(selected_group);
-                    gi.remove_task((&task_ro.0, task_ro.1));
+                    gi.archive_task(selected_task);
 
-                    if parent_of_deleted != -1 {
-                        gi.update_parents_to_check_if_all_completed(parent_of_deleted as usize);
+                    let tasks_left = gi.get_tasks_and_subtasks_count().0;
+                    if data_manager.selected_task >= tasks_left {
+                        data_manager.selected_task = if tasks_left > 0 { tasks_left - 1 } else { 0 };
                     }
 
-                    if selected_task == gi.get_tasks_and_subtasks_count().0 && selected_task > 0 {
-                        data_manager.selected_task -= 1;
-                    }
+                    data_manager.load_folding(selected_group);
+                    let _ = data_manager.save_state();
+                } else if data_manager.config.get_key("fold_subtasks") == key_code.code {
+                    if data_manager.get_group_items().is_empty() { return; }
+                    if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
 
-                    if data_manager.folded_state.contains_key(&data_manager.selected_task) {
-                        data_manager.folded_state.remove(&data_manager.selected_task);
+                    data_manager.apply();
+
+
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
+                    let selected_group = data_manager.selected_group;
+                    let gi = data_manager.get_group(selected_group);
+
+                    TaskItem::fold(GroupItem::get_task_recursive(selected_task, &mut gi.get_tasks_mut()).unwrap().0);
+                    DataManager::calculate_folded_hasmap(data_manager, data_manager.selected_task);
+
+                    let _ = data_manager.save_state();
+                } else if data_manager.config.get_key("set_due_date") == key_code.code {
+                    if data_manager.get_group_items().is_empty() { return; }
+                    if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
+
+                    self.layout_common.input_mode = InputMode::DueDate;
+                    self.is_due_date_invalid = false;
+
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
+                    let gi = data_manager.get_group_read_only(data_manager.selected_group);
+                    self.layout_common.input = GroupItem::get_task_recursive_read_only(selected_task, gi.get_tasks()).unwrap().0.get_due_string();
+                    self.layout_common.cursor_pos = self.layout_common.input.width();
+
+                    LayoutCommon::recalculate_input_string_starting_point(&mut self.layout_common);
+                } else if data_manager.config.get_key("raise_priority") == key_code.code || data_manager.config.get_key("lower_priority") == key_code.code {
+                    if data_manager.get_group_items().is_empty() { return; }
+                    if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
+
+                    data_manager.apply();
+
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
+                    let raise = data_manager.config.get_key("raise_priority") == key_code.code;
+                    let gi = data_manager.get_group(data_manager.selected_group);
+                    if raise {
+                        gi.raise_task_priority(selected_task);
+                    } else {
+                        gi.lower_task_priority(selected_task);
                     }
 
+                    // The task may have moved on screen if the group is sorted by priority
+                    data_manager.selected_task = data_manager.get_group_read_only(data_manager.selected_group).get_task_position(selected_task).unwrap();
                     data_manager.load_folding(data_manager.selected_group);
-                    data_manager.save_state();
-                } else if data_manager.config.get_key("complete_or_uncomplete_task") == key_code.code {
+                    let _ = data_manager.save_state();
+                } else if data_manager.config.get_key("sort_by_priority") == key_code.code {
                     if data_manager.get_group_items().is_empty() { return; }
-                    if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
 
                     data_manager.apply();
 
-                    let selected_task = data_manager.selected_task;
+                    let selected_task = data_manager.get_selected_task_id();
                     let gi = data_manager.get_group(data_manager.selected_group);
-                    gi.set_task_and_subtasks_done_or_undone(selected_task, None);
+                    gi.sorted_by_priority = !gi.sorted_by_priority;
 
-                    data_manager.save_state();
-                } else if data_manager.config.get_key("fold_subtasks") == key_code.code {
+                    if let Some(selected_task) = selected_task {
+                        data_manager.selected_task = data_manager.get_group_read_only(data_manager.selected_group).get_task_position(selected_task).unwrap();
+                    }
+                    data_manager.load_folding(data_manager.selected_group);
+                    let _ = data_manager.save_state();
+                } else if data_manager.config.get_key("filter_by_tags") == key_code.code {
+                    self.layout_common.input_mode = InputMode::TagFilter;
+                    self.layout_common.input = data_manager.tag_filter.join(" ");
+                    self.layout_common.cursor_pos = self.layout_common.input.width();
+
+                    LayoutCommon::recalculate_input_string_starting_point(&mut self.layout_common);
+                } else if data_manager.config.get_key("edit_notes") == key_code.code {
                     if data_manager.get_group_items().is_empty() { return; }
                     if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
 
-                    data_manager.apply();
+                    self.layout_common.input_mode = InputMode::Notes;
 
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
+                    let gi = data_manager.get_group_read_only(data_manager.selected_group);
+                    let task = GroupItem::get_task_recursive_read_only(selected_task, gi.get_tasks()).unwrap().0;
+                    self.notes_task_name = task.name.clone();
+                    self.layout_common.input = task.notes.clone();
+                    self.layout_common.cursor_pos = self.layout_common.input.chars().count();
+                } else if data_manager.config.get_key("move_task_up") == key_code.code || data_manager.config.get_key("move_task_down") == key_code.code ||
+                    data_manager.config.get_key("indent_task") == key_code.code || data_manager.config.get_key("outdent_task") == key_code.code {
+                    if data_manager.get_group_items().is_empty() { return; }
+                    if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
 
-                    let selected_task = data_manager.selected_task;
-                    let selected_group = data_manager.selected_group;
-                    let gi = data_manager.get_group(selected_group);
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
+                    let config = &data_manager.config;
+                    let (move_up, move_down, indent) = (config.get_key("move_task_up") == key_code.code, config.get_key("move_task_down") == key_code.code, config.get_key("indent_task") == key_code.code);
 
-                    TaskItem::fold(GroupItem::get_task_recursive(selected_task, &mut gi.get_tasks_mut()).unwrap().0);
-                    DataManager::calculate_folded_hasmap(data_manager, data_manager.selected_task);
+                    // Moving and indenting go by the stored order of the siblings, which the priority view doesn't show
+                    let gi_ro = data_manager.get_group_read_only(data_manager.selected_group);
+                    if gi_ro.sorted_by_priority && (move_up || move_down || indent) {
+                        data_manager.status_message = Some(format!(" Tasks can't be moved or indented while sorted by priority, press '{}' to stop sorting", data_manager.config.get_key_name("sort_by_priority")));
+                        return;
+                    }
+
+                    let task = GroupItem::get_task_recursive_read_only(selected_task, gi_ro.get_tasks()).unwrap();
+                    let siblings_count = if task.0.parent == -1 { gi_ro.get_tasks().len() } else { GroupItem::get_task_recursive_read_only(task.0.parent as usize, gi_ro.get_tasks()).unwrap().0.tasks.len() };
+                    let can_move = if move_up || indent { task.1 > 0 } else if move_down { (task.1 as usize) + 1 < siblings_count } else { task.0.parent != -1 };
+                    if !can_move {
+                        return;
+                    }
+
+                    data_manager.apply();
 
-                    data_manager.save_state();
+                    let gi = data_manager.get_group(data_manager.selected_group);
+                    if move_up || move_down {
+                        gi.move_task(selected_task, move_up);
+                    } else if indent {
+                        gi.indent_task(selected_task);
+                    } else {
+                        gi.outdent_task(selected_task);
+                    }
+
+                    data_manager.selected_task = data_manager.get_group_read_only(data_manager.selected_group).get_task_position(selected_task).unwrap();
+                    data_manager.load_folding(data_manager.selected_group);
+                    let _ = data_manager.save_state();
+                } else if data_manager.config.get_key("move_task_to_group") == key_code.code || data_manager.config.get_key("copy_task_to_group") == key_code.code {
+                    if data_manager.get_group_items().is_empty() { return; }
+                    if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
+                    if data_manager.is_selected_task_hidden() { return; }
+
+                    self.layout_common.input_mode = InputMode::GroupPicker;
+                    self.is_copying_to_group = data_manager.config.get_key("copy_task_to_group") == key_code.code;
+                    self.picked_group = data_manager.selected_group;
                 } else if data_manager.config.get_key("down_task_or_subtask") == key_code.code {
                     if data_manager.get_group_items().is_empty() { return; }
                     if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
 
                     let tasks = data_manager.get_group_items()[data_manager.selected_group].get_tasks_and_subtasks_count();
-                    let selected_task = data_manager.selected_task;
-                    let gi = data_manager.get_group_read_only(data_manager.selected_group);
-                    let task = gi.get_tasks().last().unwrap();
-                    let is_last_and_folded = task.id == selected_task && task.folded;
-
-                    if data_manager.selected_task < tasks.0 - 1 && !is_last_and_folded {
-                        for (_, entry) in data_manager.folded_state.iter() {
-                            let next_task = data_manager.selected_task + 1;
-                            if entry.contains(&next_task) {
-                                if data_manager.selected_task + entry.len() < tasks.0 - 1 {
-                                    data_manager.selected_task += entry.len();
-                                }
-                                break;
-                            }
-                        }
+                    let hidden = data_manager.get_hidden_positions();
+
+                    let mut next_task = data_manager.selected_task + 1;
+                    while next_task < tasks.0 && hidden.contains(&next_task) {
+                        next_task += 1;
+                    }
 
-                        data_manager.selected_task += 1;
+                    if next_task < tasks.0 {
+                        data_manager.selected_task = next_task;
                     }
                 } else if data_manager.config.get_key("up_task_or_subtask") == key_code.code {
                     if data_manager.get_group_items().is_empty() { return; }
                     if data_manager.get_group_items()[data_manager.selected_group].get_tasks().is_empty() { return; }
 
-                    if data_manager.selected_task > 0 {
+                    let hidden = data_manager.get_hidden_positions();
 
-                        let mut biggest_group = 0;
-                        for (_, entry) in data_manager.folded_state.iter() {
-                            let previous_task = data_manager.selected_task - 1;
-                            if entry.contains(&previous_task) && entry.len() > biggest_group {
-                                biggest_group = entry.len();
-                            }
+                    let mut previous_task = data_manager.selected_task;
+                    while previous_task > 0 {
+                        previous_task -= 1;
+                        if !hidden.contains(&previous_task) {
+                            data_manager.selected_task = previous_task;
+                            break;
                         }
-
-                        data_manager.selected_task -= 1 + biggest_group;
                     }
                 }
             },
@@ -327,12 +556,12 @@ impl LayoutCommonTrait for TaskLayout {
                 if data_manager.config.get_key("apply_add_or_edit_task_or_subtask") == key_code.code {
                     data_manager.apply();
 
-                    let selected_task = data_manager.selected_task;
+                    let selected_task = data_manager.get_selected_task_id();
                     let gi = data_manager.get_group(data_manager.selected_group);
                     if !self.is_adding_subtask {
                         gi.add_task(self.layout_common.input.drain(..).collect());
                     } else {
-                        let new_id = gi.add_subtask(self.layout_common.input.drain(..).collect(), selected_task);
+                        let new_id = gi.add_subtask(self.layout_common.input.drain(..).collect(), selected_task.unwrap());
 
                         let selected_task = new_id;
                         let gi = data_manager.get_group(data_manager.selected_group);
@@ -340,7 +569,7 @@ impl LayoutCommonTrait for TaskLayout {
                     }
 
                     self.layout_common.input_mode = InputMode::Navigate;
-                    data_manager.save_state();
+                    let _ = data_manager.save_state();
                 } else {
                     <TaskLayout as LayoutCommonTrait>::poll_common_keys_input_mode(&key_code, &mut self.layout_common)
                 }
@@ -349,11 +578,92 @@ impl LayoutCommonTrait for TaskLayout {
                 if data_manager.config.get_key("apply_add_or_edit_task_or_subtask") == key_code.code {
                     data_manager.apply();
 
-                    let selected_task = data_manager.selected_task;
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
                     let gi = data_manager.get_group(data_manager.selected_group);
                     gi.edit_sub_task(selected_task, self.layout_common.input.drain(..).collect());
                     self.layout_common.input_mode = InputMode::Navigate;
-                    data_manager.save_state();
+                    let _ = data_manager.save_state();
+                } else {
+                    <TaskLayout as LayoutCommonTrait>::poll_common_keys_input_mode(&key_code, &mut self.layout_common)
+                }
+            },
+            InputMode::GroupPicker => {
+                if data_manager.config.get_key("apply_add_or_edit_task_or_subtask") == key_code.code {
+                    self.layout_common.input_mode = InputMode::Navigate;
+
+                    let selected_group = data_manager.selected_group;
+                    if self.picked_group == selected_group && !self.is_copying_to_group {
+                        return;
+                    }
+
+                    data_manager.apply();
+
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
+                    data_manager.move_task_to_group(selected_task, selected_group, self.picked_group, self.is_copying_to_group);
+
+                    let tasks_left = data_manager.get_group_read_only(selected_group).get_tasks_and_subtasks_count().0;
+                    if data_manager.selected_task >= tasks_left {
+                        data_manager.selected_task = if tasks_left > 0 { tasks_left - 1 } else { 0 };
+                    }
+
+                    data_manager.load_folding(selected_group);
+                    let _ = data_manager.save_state();
+                } else if data_manager.config.get_key("up_task_or_subtask") == key_code.code {
+                    if self.picked_group > 0 {
+                        self.picked_group -= 1;
+                    }
+                } else if data_manager.config.get_key("down_task_or_subtask") == key_code.code {
+                    if self.picked_group < data_manager.get_group_items().len() - 1 {
+                        self.picked_group += 1;
+                    }
+                } else if key_code.code == KeyCode::Esc {
+                    self.layout_common.input_mode = InputMode::Navigate;
+                }
+            },
+            InputMode::Confirm => {
+                if data_manager.config.get_key("accept_confirmation") == key_code.code {
+                    TaskLayout::delete_selected_task(data_manager);
+                }
+                self.layout_common.input_mode = InputMode::Navigate;
+            },
+            InputMode::Notes => {
+                if data_manager.config.get_key("apply_notes") == key_code.code {
+                    data_manager.apply();
+
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
+                    let gi = data_manager.get_group(data_manager.selected_group);
+                    gi.set_task_notes(selected_task, self.layout_common.input.drain(..).collect());
+                    self.layout_common.input_mode = InputMode::Navigate;
+                    let _ = data_manager.save_state();
+                } else {
+                    <TaskLayout as LayoutCommonTrait>::poll_multiline_keys_input_mode(&key_code, &mut self.layout_common)
+                }
+            },
+            InputMode::TagFilter => {
+                if data_manager.config.get_key("apply_add_or_edit_task_or_subtask") == key_code.code {
+                    data_manager.tag_filter = self.layout_common.input.drain(..).collect::<String>().split_whitespace().map(|tag| tag.to_lowercase()).collect();
+                    data_manager.select_visible_task();
+                    self.layout_common.input_mode = InputMode::Navigate;
+                } else {
+                    <TaskLayout as LayoutCommonTrait>::poll_common_keys_input_mode(&key_code, &mut self.layout_common)
+                }
+            },
+            InputMode::DueDate => {
+                if data_manager.config.get_key("apply_add_or_edit_task_or_subtask") == key_code.code {
+                    let due = TaskItem::parse_due(self.layout_common.input.as_str());
+                    if due.is_err() {
+                        self.is_due_date_invalid = true;
+                        return;
+                    }
+
+                    data_manager.apply();
+
+                    let selected_task = data_manager.get_selected_task_id().unwrap();
+                    let gi = data_manager.get_group(data_manager.selected_group);
+                    gi.set_task_due(selected_task, due.unwrap());
+                    self.layout_common.input.clear();
+                    self.layout_common.input_mode = InputMode::Navigate;
+                    let _ = data_manager.save_state();
                 } else {
                     <TaskLayout as LayoutCommonTrait>::poll_common_keys_input_mode(&key_code, &mut self.layout_common)
                 }
@@ -367,8 +677,9 @@ impl LayoutCommonTrait for TaskLayout {
     }
 
     fn create_and_render_base_block<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>) {
+        let title = if app.data_manager.tag_filter.is_empty() { "Tasks".to_string() } else { format!("Tasks (filter: {})", app.data_manager.tag_filter.join(" ")) };
         let mut tasks_block = Block::default()
-            .title("Tasks")
+            .title(title)
             .borders(Borders::ALL)
             .style(Style::default().fg(app.data_manager.config.get_color("task", "border_color")));
 
@@ -387,8 +698,10 @@ impl LayoutCommonTrait for TaskLayout {
             return;
         }
 
-        let tasks = app.data_manager.get_group_items()[ app.data_manager.selected_group].get_tasks();
-        let items_list = TaskLayout::recursive_sub_tasks(&app.task_layout, &app.data_manager, tasks, frame_size);
+        let gi = &app.data_manager.get_group_items()[app.data_manager.selected_group];
+        let positions : HashMap<usize, usize> = gi.get_flattened_task_ids().into_iter().enumerate().map(|(position, id)| (id, position)).collect();
+        let filtered_out = app.data_manager.get_task_ids_filtered_out();
+        let items_list = TaskLayout::recursive_sub_tasks(&app.task_layout, &app.data_manager, gi.get_tasks(), &positions, &filtered_out, frame_size);
 
         let items = List::new(items_list)
             .block(Block::default().borders(Borders::NONE)).style(Style::default());
@@ -397,8 +710,30 @@ impl LayoutCommonTrait for TaskLayout {
     }
 
     fn create_and_render_edit_mode<B: Backend>(f: &mut Frame<B>, app: &mut App, chunk: &Vec<Rect>) {
-        let title : String = if app.task_layout.layout_common.input_mode == InputMode::Add {  if app.task_layout.is_adding_subtask { "Add subtask".to_string() } else { "Add task".to_string() } } else { "Edit task".to_string() };
+        let title : String = match app.task_layout.layout_common.input_mode {
+            InputMode::Add => if app.task_layout.is_adding_subtask { "Add subtask".to_string() } else { "Add task".to_string() },
+            InputMode::TagFilter => "Filter by tags (e.g. #backend @alice, empty to clear)".to_string(),
+            InputMode::DueDate => if app.task_layout.is_due_date_invalid { "Invalid date, use YYYY-MM-DD [HH:MM]".to_string() } else { "Due date (YYYY-MM-DD [HH:MM], empty to clear)".to_string() },
+            _ => "Edit task".to_string()
+        };
         app.task_layout.width_of_chunk = chunk[1].width as usize;
+
+        if app.task_layout.layout_common.input_mode == InputMode::GroupPicker {
+            TaskLayout::render_group_picker(f, app, chunk);
+            return;
+        }
+
+        if app.task_layout.layout_common.input_mode == InputMode::Confirm {
+            <TaskLayout as LayoutCommonTrait>::render_confirmation(f, app.task_layout.delete_question.as_str(), chunk);
+            return;
+        }
+
+        if app.task_layout.layout_common.input_mode == InputMode::Notes {
+            let title = format!("Notes of '{}' (Enter new line, Tab save, Esc cancel)", app.task_layout.notes_task_name);
+            <TaskLayout as LayoutCommonTrait>::render_multiline_input_mode(f, &mut app.task_layout.layout_common, title.as_str(), chunk);
+            return;
+        }
+
         <TaskLayout as LayoutCommonTrait>::render_common_input_mode(f, &mut app.task_layout.layout_common, title.as_str(), chunk);
     }
 }
\ No newline at end of file
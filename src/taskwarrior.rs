@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use chrono::{Local, NaiveTime};
+use serde_json::{json, Map, Value};
+
+use crate::data_manager::{DataManager, GroupItem, TaskItem};
+use crate::enums::Priority;
+use crate::import::{add_imported_tasks, from_utc_date, to_utc_date, ImportedTask};
+
+/// Tasks in the JSON format of `task export` and `task import`. The group is the project, and a task with subtasks
+/// depends on them, as it can't be completed before them. The uuids come from the group and task ids, so exporting
+/// again updates the same tasks in Taskwarrior instead of adding new ones.
+pub fn export_groups(groups: &Vec<&GroupItem>) -> String {
+    let mut tasks : Vec<Value> = Vec::new();
+    for group in groups {
+        export_tasks_recursive(group, group.get_tasks(), &mut tasks);
+    }
+
+    return serde_json::to_string_pretty(&Value::Array(tasks)).unwrap();
+}
+
+fn export_tasks_recursive(group: &GroupItem, tasks: &Vec<TaskItem>, result: &mut Vec<Value>) {
+    let now = Local::now().naive_local();
+
+    for task in tasks {
+        let mut object = Map::new();
+        object.insert("uuid".to_string(), json!(get_uuid(group, task)));
+        object.insert("description".to_string(), json!(task.name));
+        object.insert("status".to_string(), json!(if task.done { "completed" } else { "pending" }));
+        object.insert("project".to_string(), json!(group.name));
+        object.insert("entry".to_string(), json!(to_utc_date(task.created_at.unwrap_or(now))));
+
+        if let Some(edited_at) = task.edited_at {
+            object.insert("modified".to_string(), json!(to_utc_date(edited_at)));
+        }
+        if task.done {
+            object.insert("end".to_string(), json!(to_utc_date(task.completed_at.unwrap_or(now))));
+        }
+        if let Some(due_date) = task.due_date {
+            object.insert("due".to_string(), json!(to_utc_date(due_date.and_time(task.due_time.unwrap_or(NaiveTime::MIN)))));
+        }
+
+        let priority = match task.priority {
+            Priority::Urgent | Priority::High => Some("H"),
+            Priority::Medium => Some("M"),
+            Priority::Low => Some("L"),
+            Priority::None => None
+        };
+        if let Some(priority) = priority {
+            object.insert("priority".to_string(), json!(priority));
+        }
+
+        let tags : Vec<String> = task.get_tags().iter().map(|tag| tag[1..].to_string()).collect();
+        if !tags.is_empty() {
+            object.insert("tags".to_string(), json!(tags));
+        }
+        if !task.tasks.is_empty() {
+            let depends : Vec<String> = task.tasks.iter().map(|subtask| get_uuid(group, subtask)).collect();
+            object.insert("depends".to_string(), json!(depends));
+        }
+        if !task.notes.is_empty() {
+            object.insert("annotations".to_string(), json!([{ "entry": to_utc_date(task.edited_at.unwrap_or(now)), "description": task.notes }]));
+        }
+
+        result.push(Value::Object(object));
+        export_tasks_recursive(group, &task.tasks, result);
+    }
+}
+
+/// Adds the tasks of a Taskwarrior export, either a JSON array or one task per line, to the group of their project.
+/// Tasks without a project go to `default_group`, deleted tasks and recurring templates are skipped. A task becomes
+/// a subtask of the first task of its project depending on it. Returns how many tasks were added.
+pub fn import(data_manager: &mut DataManager, content: &str, default_group: &str) -> Result<usize, String> {
+    let objects : Vec<Value> = match serde_json::from_str::<Value>(content) {
+        Ok(Value::Array(objects)) => objects,
+        Ok(object) => vec![object],
+        Err(_) => content.lines().map(|line| line.trim().trim_end_matches(',')).filter(|line| !line.is_empty() && !line.eq(&"[") && !line.eq(&"]"))
+            .map(|line| serde_json::from_str::<Value>(line).map_err(|error| format!("Not a Taskwarrior export: {}", error)))
+            .collect::<Result<Vec<Value>, String>>()?
+    };
+
+    let mut tasks : Vec<ImportedTask> = Vec::new();
+    let mut depended_by : HashMap<String, String> = HashMap::new();
+
+    for object in &objects {
+        let field = |name: &str| object.get(name).and_then(|value| value.as_str());
+        let date = |name: &str| field(name).and_then(from_utc_date);
+
+        let status = field("status").unwrap_or("pending");
+        if status.eq("deleted") || status.eq("recurring") {
+            continue;
+        }
+
+        let description = field("description").ok_or(format!("Task without a description: {}", object))?;
+        let mut task = ImportedTask {
+            group: field("project").unwrap_or(default_group).to_string(),
+            key: field("uuid").map(|uuid| uuid.to_string()),
+            parent: None,
+            fields: TaskItem::new(description.to_string(), 0, -1)
+        };
+
+        if let Some(tags) = object.get("tags").and_then(|tags| tags.as_array()) {
+            let existing_tags = task.fields.get_tags();
+            for tag in tags.iter().filter_map(|tag| tag.as_str()) {
+                if !existing_tags.iter().any(|existing| existing[1..].eq_ignore_ascii_case(tag)) {
+                    task.fields.name.push_str(&format!(" #{}", tag));
+                }
+            }
+        }
+
+        task.fields.created_at = date("entry").or(task.fields.created_at);
+        task.fields.edited_at = date("modified").or(task.fields.created_at);
+        if status.eq("completed") {
+            task.fields.done = true;
+            task.fields.completed_at = date("end").or(task.fields.edited_at);
+        }
+        if let Some(due) = date("due") {
+            task.fields.due_date = Some(due.date());
+            task.fields.due_time = if due.time() == NaiveTime::MIN { None } else { Some(due.time()) };
+        }
+        task.fields.priority = match field("priority") {
+            Some("H") => Priority::High,
+            Some("M") => Priority::Medium,
+            Some("L") => Priority::Low,
+            _ => Priority::None
+        };
+
+        if let Some(annotations) = object.get("annotations").and_then(|annotations| annotations.as_array()) {
+            let notes : Vec<&str> = annotations.iter().filter_map(|annotation| annotation.get("description").and_then(|description| description.as_str())).collect();
+            task.fields.notes = notes.join("\n");
+        }
+
+        // Older versions write the dependencies as a comma separated string
+        let depends : Vec<String> = match object.get("depends") {
+            Some(Value::Array(depends)) => depends.iter().filter_map(|uuid| uuid.as_str()).map(|uuid| uuid.to_string()).collect(),
+            Some(Value::String(depends)) => depends.split(',').map(|uuid| uuid.trim().to_string()).collect(),
+            _ => Vec::new()
+        };
+        if let Some(uuid) = &task.key {
+            for dependency in depends {
+                depended_by.entry(dependency).or_insert(uuid.clone());
+            }
+        }
+
+        tasks.push(task);
+    }
+
+    for task in &mut tasks {
+        task.parent = task.key.as_ref().and_then(|uuid| depended_by.get(uuid)).cloned();
+    }
+
+    return Ok(add_imported_tasks(data_manager, tasks));
+}
+
+/// A version 4 like uuid made of the group id, the task id and the task creation time.
+fn get_uuid(group: &GroupItem, task: &TaskItem) -> String {
+    let created = task.created_at.map(|created_at| created_at.and_utc().timestamp() as u64).unwrap_or(0);
+    let group_id = group.get_id() as u64;
+
+    return format!("{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}", created & 0xffff_ffff, (created >> 32) & 0xffff, group_id & 0xfff,
+                   0x8000 | ((group_id >> 12) & 0x3fff), task.id as u64 & 0xffff_ffff_ffff);
+}
@@ -0,0 +1,169 @@
+use chrono::{Local, NaiveDate, NaiveDateTime};
+
+use crate::data_manager::{DataManager, GroupItem, TaskItem};
+use crate::enums::Priority;
+use crate::import::{add_imported_tasks, ImportedTask};
+
+/// One line per task in the todo.txt format (http://todotxt.org). The group is the `+project` at the end of the line,
+/// with its spaces turned into underscores, and subtasks point to their parent with `parent:<id>`, where `id:<id>`
+/// is the id of the task in its group. Completed tasks keep their priority as `pri:<letter>`, as the format drops it.
+/// Due dates are `due:2026-10-20`, or `due:2026-10-20T15:30` when the task has a due time.
+pub fn export_groups(groups: &Vec<&GroupItem>) -> String {
+    let mut todo_txt = String::new();
+    for group in groups {
+        export_tasks_recursive(group.get_tasks(), &to_project(&group.name), &mut todo_txt);
+    }
+
+    return todo_txt;
+}
+
+fn export_tasks_recursive(tasks: &Vec<TaskItem>, project: &str, todo_txt: &mut String) {
+    for task in tasks {
+        let mut line : Vec<String> = Vec::new();
+        if task.done {
+            line.push("x".to_string());
+            if let Some(completed_at) = task.completed_at {
+                line.push(completed_at.format("%Y-%m-%d").to_string());
+                if let Some(created_at) = task.created_at {
+                    line.push(created_at.format("%Y-%m-%d").to_string());
+                }
+            }
+        } else {
+            if let Some(letter) = priority_to_letter(task.priority) {
+                line.push(format!("({})", letter));
+            }
+            if let Some(created_at) = task.created_at {
+                line.push(created_at.format("%Y-%m-%d").to_string());
+            }
+        }
+
+        line.push(task.name.replace(['\n', '\r'], " "));
+        line.push(format!("+{}", project));
+
+        match (task.due_date, task.due_time) {
+            (Some(due_date), Some(due_time)) => line.push(format!("due:{}", due_date.and_time(due_time).format("%Y-%m-%dT%H:%M"))),
+            (Some(due_date), None) => line.push(format!("due:{}", due_date.format("%Y-%m-%d"))),
+            _ => {}
+        }
+        if task.done {
+            if let Some(letter) = priority_to_letter(task.priority) {
+                line.push(format!("pri:{}", letter));
+            }
+        }
+        if !task.tasks.is_empty() {
+            line.push(format!("id:{}", task.id));
+        }
+        if task.parent != -1 {
+            line.push(format!("parent:{}", task.parent));
+        }
+
+        todo_txt.push_str(&line.join(" "));
+        todo_txt.push('\n');
+
+        export_tasks_recursive(&task.tasks, project, todo_txt);
+    }
+}
+
+/// Adds the tasks of a todo.txt file. Each goes to the group of its last `+project`, matched with underscores as
+/// spaces and created if there is none, and tasks without a project go to `default_group`. `parent:` keys rebuild
+/// the subtasks, the ids themselves are new. Keys whose value doesn't parse, like `due:tomorrow`, stay in the text.
+/// Returns how many tasks were added.
+pub fn import(data_manager: &mut DataManager, content: &str, default_group: &str) -> Result<usize, String> {
+    let mut tasks : Vec<ImportedTask> = content.lines().filter_map(|line| parse_line(line, default_group)).collect();
+
+    for task in &mut tasks {
+        if let Some(gi) = data_manager.get_group_items().iter().find(|gi| to_project(&gi.name).eq(&task.group)) {
+            task.group = gi.name.clone();
+        }
+    }
+
+    return Ok(add_imported_tasks(data_manager, tasks));
+}
+
+fn parse_line(line: &str, default_group: &str) -> Option<ImportedTask> {
+    let mut words = line.split_whitespace().peekable();
+    let mut task = ImportedTask { group: default_group.to_string(), key: None, parent: None, fields: TaskItem::new(String::new(), 0, -1) };
+
+    let parse_date = |word: &str| NaiveDate::parse_from_str(word, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0));
+
+    let mut completed_at = None;
+    if words.peek() == Some(&"x") {
+        words.next();
+        task.fields.done = true;
+        completed_at = words.peek().and_then(|word| parse_date(word));
+        if completed_at.is_some() {
+            words.next();
+        }
+    } else if let Some(letter) = words.peek().and_then(|word| parse_priority(word)) {
+        words.next();
+        task.fields.priority = letter_to_priority(letter);
+    }
+
+    if let Some(created_at) = words.peek().and_then(|word| parse_date(word)) {
+        words.next();
+        task.fields.created_at = Some(created_at);
+    }
+
+    let mut name : Vec<&str> = Vec::new();
+    for word in words {
+        match word.split_once(':') {
+            Some(("due", value)) if parse_date(value).is_some() => task.fields.due_date = parse_date(value).map(|due| due.date()),
+            Some(("due", value)) if NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M").is_ok() => {
+                let due = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M").unwrap();
+                task.fields.due_date = Some(due.date());
+                task.fields.due_time = Some(due.time());
+            },
+            Some(("pri", value)) if value.len() == 1 && value.chars().all(|c| c.is_ascii_uppercase()) => task.fields.priority = letter_to_priority(value.chars().next().unwrap()),
+            Some(("id", value)) if value.parse::<usize>().is_ok() => task.key = Some(value.to_string()),
+            Some(("parent", value)) if value.parse::<usize>().is_ok() => task.parent = Some(value.to_string()),
+            _ => name.push(word)
+        }
+    }
+
+    if let Some(position) = name.iter().rposition(|word| word.len() > 1 && word.starts_with('+')) {
+        task.group = name.remove(position)[1..].to_string();
+    }
+
+    task.fields.name = name.join(" ");
+    if task.fields.name.is_empty() {
+        return None;
+    }
+
+    if task.fields.done {
+        task.fields.completed_at = completed_at.or(Some(Local::now().naive_local()));
+    }
+
+    return Some(task);
+}
+
+fn parse_priority(word: &str) -> Option<char> {
+    let letter = word.strip_prefix('(')?.strip_suffix(')')?;
+    if letter.len() != 1 || !letter.chars().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+
+    return letter.chars().next();
+}
+
+fn to_project(group_name: &str) -> String {
+    return group_name.split_whitespace().collect::<Vec<&str>>().join("_");
+}
+
+fn priority_to_letter(priority: Priority) -> Option<char> {
+    return match priority {
+        Priority::Urgent => Some('A'),
+        Priority::High => Some('B'),
+        Priority::Medium => Some('C'),
+        Priority::Low => Some('D'),
+        Priority::None => None
+    };
+}
+
+fn letter_to_priority(letter: char) -> Priority {
+    return match letter {
+        'A' => Priority::Urgent,
+        'B' => Priority::High,
+        'C' => Priority::Medium,
+        _ => Priority::Low
+    };
+}